@@ -0,0 +1,192 @@
+use chrono::DateTime;
+use chrono::Utc;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::requests::logs::NewLog;
+
+/// web server access log formats
+///
+/// the response time, rounded to milliseconds for `NewLog`, is taken from the last field of the
+/// line.
+/// fields written as `key=value` (like `rt=0.123`) are also accepted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AccessLogFormat {
+    /// Common Log Format followed by the response time in microseconds (apache `%D`),
+    /// the user agent is `-`
+    Common,
+    /// Combined Log Format followed by the response time in microseconds (apache `%D`)
+    Combined,
+    /// nginx `combined` followed by `$request_time` in seconds
+    #[default]
+    Nginx,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, derive_more::Display, derive_more::Error)]
+pub enum AccessLogError {
+    #[display(fmt = "missing {}", _0)]
+    MissingField(#[error(not(source))] &'static str),
+    #[display(fmt = "unterminated {}", _0)]
+    Unterminated(#[error(not(source))] &'static str),
+    #[display(fmt = "invalid timestamp `{}`", _0)]
+    InvalidTimestamp(#[error(not(source))] String),
+    #[display(fmt = "invalid response time `{}`", _0)]
+    InvalidResponseTime(#[error(not(source))] String),
+}
+
+const TIMESTAMP_FORMAT: &str = "%d/%b/%Y:%H:%M:%S %z";
+
+impl AccessLogFormat {
+    /// parse one line of an access log
+    pub fn parse(self, line: &str) -> Result<NewLog, AccessLogError> {
+        let mut fields = Fields { rest: line };
+
+        // host ident authuser [timestamp] "request" status bytes
+        fields.bare("host")?;
+        fields.bare("ident")?;
+        fields.bare("authuser")?;
+        let timestamp = fields.bracketed("timestamp")?;
+        fields.quoted("request")?;
+        fields.bare("status")?;
+        fields.bare("bytes")?;
+
+        let user_agent = match self {
+            AccessLogFormat::Common => "-".to_string(),
+            AccessLogFormat::Combined | AccessLogFormat::Nginx => {
+                fields.quoted("referer")?;
+                fields.quoted("user agent")?
+            }
+        };
+
+        // custom formats may add fields between the user agent and the response time
+        let mut response_time = None;
+        while let Some(field) = fields.any()? {
+            response_time = Some(field);
+        }
+        let response_time = response_time.ok_or(AccessLogError::MissingField("response time"))?;
+
+        Ok(NewLog {
+            user_agent,
+            response_time: self.response_time(&response_time)?,
+            timestamp: Some(parse_timestamp(timestamp)?),
+            id: None,
+        })
+    }
+
+    fn response_time(self, field: &str) -> Result<i32, AccessLogError> {
+        let invalid = || AccessLogError::InvalidResponseTime(field.to_string());
+        let value = field
+            .rsplit_once('=')
+            .map(|(_, value)| value)
+            .unwrap_or(field);
+
+        match self {
+            AccessLogFormat::Common | AccessLogFormat::Combined => value
+                .parse::<i64>()
+                .ok()
+                .filter(|micros| *micros >= 0)
+                .and_then(|micros| micros.checked_add(500))
+                .and_then(|micros| i32::try_from(micros / 1000).ok())
+                .ok_or_else(invalid),
+            AccessLogFormat::Nginx => value
+                .parse::<f64>()
+                .ok()
+                .filter(|seconds| seconds.is_finite() && *seconds >= 0.0)
+                .map(|seconds| (seconds * 1000.0).round())
+                .filter(|millis| *millis <= i32::MAX as f64)
+                .map(|millis| millis as i32)
+                .ok_or_else(invalid),
+        }
+    }
+}
+
+fn parse_timestamp(timestamp: &str) -> Result<DateTime<Utc>, AccessLogError> {
+    DateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT)
+        .map(|timestamp| timestamp.with_timezone(&Utc))
+        .map_err(|_| AccessLogError::InvalidTimestamp(timestamp.to_string()))
+}
+
+// ascii character written as 2 hex digits, other bytes are kept escaped
+fn hex_escape(digits: &str) -> Option<char> {
+    let digits = digits
+        .get(..2)
+        .filter(|digits| digits.bytes().all(|b| b.is_ascii_hexdigit()))?;
+    u8::from_str_radix(digits, 16)
+        .ok()
+        .filter(u8::is_ascii)
+        .map(char::from)
+}
+
+// space separated fields, some of them in `[...]` or `"..."`
+struct Fields<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Fields<'a> {
+    fn next_bare(&mut self) -> Option<&'a str> {
+        let rest = self.rest.trim_start();
+        if rest.is_empty() {
+            return None;
+        }
+        let end = rest.find(' ').unwrap_or(rest.len());
+        self.rest = &rest[end..];
+        Some(&rest[..end])
+    }
+
+    fn bare(&mut self, name: &'static str) -> Result<&'a str, AccessLogError> {
+        self.next_bare().ok_or(AccessLogError::MissingField(name))
+    }
+
+    fn bracketed(&mut self, name: &'static str) -> Result<&'a str, AccessLogError> {
+        let rest = self.rest.trim_start();
+        let rest = rest
+            .strip_prefix('[')
+            .ok_or(AccessLogError::MissingField(name))?;
+        let end = rest.find(']').ok_or(AccessLogError::Unterminated(name))?;
+        self.rest = &rest[end + 1..];
+        Ok(&rest[..end])
+    }
+
+    // quotes and backslashes in the value are escaped as `\"` and `\\` (apache),
+    // or as `\x22` and `\x5C` (nginx)
+    fn quoted(&mut self, name: &'static str) -> Result<String, AccessLogError> {
+        let rest = self.rest.trim_start();
+        let rest = rest
+            .strip_prefix('"')
+            .ok_or(AccessLogError::MissingField(name))?;
+
+        let mut value = String::new();
+        let mut chars = rest.char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.rest = &rest[i + 1..];
+                    return Ok(value);
+                }
+                '\\' => match chars.next() {
+                    Some((j, 'x')) => match hex_escape(&rest[j + 1..]) {
+                        Some(escaped) => {
+                            value.push(escaped);
+                            chars.nth(1);
+                        }
+                        None => value.push_str("\\x"),
+                    },
+                    Some((_, escaped)) => value.push(escaped),
+                    None => break,
+                },
+                c => value.push(c),
+            }
+        }
+        Err(AccessLogError::Unterminated(name))
+    }
+
+    fn any(&mut self) -> Result<Option<String>, AccessLogError> {
+        match self.rest.trim_start().chars().next() {
+            None => Ok(None),
+            Some('"') => self.quoted("field").map(Some),
+            Some('[') => self.bracketed("field").map(|field| Some(field.to_string())),
+            Some(_) => Ok(self.next_bare().map(str::to_string)),
+        }
+    }
+}
@@ -0,0 +1,224 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::de;
+use serde::Deserialize;
+use serde::Deserializer;
+use serde::Serialize;
+use serde::Serializer;
+
+/// where each `NewLog` field is found in a csv file
+///
+/// written as `field:column` pairs, like `user_agent:ua,response_time:latency_ms`.
+/// a column is a header name, or a 0-based position for files without a header.
+/// fields that are not mapped are looked up by their own name in the header,
+/// or taken in the order `user_agent,response_time,timestamp,id` when there is no header.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ColumnMapping {
+    pub user_agent: Option<Column>,
+    pub response_time: Option<Column>,
+    pub timestamp: Option<Column>,
+    pub id: Option<Column>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Column {
+    Name(String),
+    Index(usize),
+}
+
+/// resolved positions of the fields in csv records
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ColumnLayout {
+    pub user_agent: usize,
+    pub response_time: usize,
+    pub timestamp: Option<usize>,
+    pub id: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, derive_more::Display, derive_more::Error)]
+pub enum ColumnError {
+    #[display(fmt = "invalid column mapping `{}`", _0)]
+    InvalidMapping(#[error(not(source))] String),
+    #[display(fmt = "unknown field `{}` in column mapping", _0)]
+    UnknownField(#[error(not(source))] String),
+    #[display(fmt = "column `{}` is not found in the header", _0)]
+    MissingColumn(#[error(not(source))] String),
+    #[display(fmt = "column `{}` is mapped by name, but the csv has no header", _0)]
+    NoHeader(#[error(not(source))] String),
+}
+
+const FIELDS: [&str; 4] = ["user_agent", "response_time", "timestamp", "id"];
+
+impl ColumnMapping {
+    fn columns(&self) -> [&Option<Column>; 4] {
+        [
+            &self.user_agent,
+            &self.response_time,
+            &self.timestamp,
+            &self.id,
+        ]
+    }
+
+    /// a record is taken as a header, when one of its fields is the name of a column
+    pub fn is_header<'a>(&self, record: impl IntoIterator<Item = &'a str>) -> bool {
+        let names = self
+            .columns()
+            .into_iter()
+            .zip(FIELDS)
+            .filter_map(|(column, field)| match column {
+                Some(Column::Name(name)) => Some(name.as_str()),
+                Some(Column::Index(_)) => None,
+                None => Some(field),
+            })
+            .collect::<Vec<_>>();
+
+        record.into_iter().any(|value| {
+            names
+                .iter()
+                .any(|name| value.trim().eq_ignore_ascii_case(name))
+        })
+    }
+
+    /// find the field positions, in the header if the csv has one
+    pub fn layout(&self, header: Option<&[&str]>) -> Result<ColumnLayout, ColumnError> {
+        let position =
+            |column: &Option<Column>, field: &str| -> Result<Option<usize>, ColumnError> {
+                match (column, header) {
+                    (Some(Column::Index(index)), _) => Ok(Some(*index)),
+                    (Some(Column::Name(name)), Some(header)) => find(header, name)
+                        .map(Some)
+                        .ok_or_else(|| ColumnError::MissingColumn(name.clone())),
+                    (Some(Column::Name(name)), None) => Err(ColumnError::NoHeader(name.clone())),
+                    (None, Some(header)) => Ok(find(header, field)),
+                    (None, None) => Ok(FIELDS.iter().position(|f| *f == field)),
+                }
+            };
+        let required = |column: &Option<Column>, field: &str| -> Result<usize, ColumnError> {
+            position(column, field)?.ok_or_else(|| ColumnError::MissingColumn(field.to_string()))
+        };
+
+        Ok(ColumnLayout {
+            user_agent: required(&self.user_agent, "user_agent")?,
+            response_time: required(&self.response_time, "response_time")?,
+            timestamp: position(&self.timestamp, "timestamp")?,
+            id: position(&self.id, "id")?,
+        })
+    }
+}
+
+/// fields of csv records in `NewLog` order, the layout is resolved on the first record
+#[derive(Debug, Clone)]
+pub struct ColumnSelector {
+    mapping: ColumnMapping,
+    layout: Option<ColumnLayout>,
+}
+
+impl ColumnSelector {
+    pub fn new(mapping: ColumnMapping) -> Self {
+        Self {
+            mapping,
+            layout: None,
+        }
+    }
+
+    /// the fields of a record, `None` when the first record is a header
+    ///
+    /// fails on the first record when the columns can't be found, the mapping is wrong for
+    /// the whole file then
+    pub fn select<'a>(&mut self, record: &[&'a str]) -> Result<Option<[&'a str; 4]>, ColumnError> {
+        if let Some(layout) = self.layout {
+            return Ok(Some(layout.select(record)));
+        }
+
+        let header = self.mapping.is_header(record.iter().copied());
+        let layout = self.mapping.layout(header.then_some(record))?;
+        self.layout = Some(layout);
+
+        Ok((!header).then(|| layout.select(record)))
+    }
+}
+
+fn find(header: &[&str], name: &str) -> Option<usize> {
+    header
+        .iter()
+        .position(|column| column.trim().eq_ignore_ascii_case(name))
+}
+
+impl ColumnLayout {
+    /// fields of a record in `NewLog` order, missing ones are empty
+    pub fn select<'a>(&self, record: &[&'a str]) -> [&'a str; 4] {
+        let field = |index: Option<usize>| {
+            index
+                .and_then(|index| record.get(index))
+                .copied()
+                .unwrap_or_default()
+        };
+        [
+            field(Some(self.user_agent)),
+            field(Some(self.response_time)),
+            field(self.timestamp),
+            field(self.id),
+        ]
+    }
+}
+
+impl fmt::Display for ColumnMapping {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pairs = self
+            .columns()
+            .into_iter()
+            .zip(FIELDS)
+            .filter_map(|(column, field)| match column {
+                Some(Column::Name(name)) => Some(format!("{field}:{name}")),
+                Some(Column::Index(index)) => Some(format!("{field}:{index}")),
+                None => None,
+            })
+            .collect::<Vec<_>>();
+        write!(f, "{}", pairs.join(","))
+    }
+}
+
+impl FromStr for ColumnMapping {
+    type Err = ColumnError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut mapping = ColumnMapping::default();
+
+        for pair in s.split(',').filter(|pair| !pair.trim().is_empty()) {
+            let (field, column) = pair
+                .split_once(':')
+                .ok_or_else(|| ColumnError::InvalidMapping(pair.to_string()))?;
+            let column = match column.trim() {
+                "" => return Err(ColumnError::InvalidMapping(pair.to_string())),
+                column => column
+                    .parse()
+                    .map(Column::Index)
+                    .unwrap_or_else(|_| Column::Name(column.to_string())),
+            };
+
+            match field.trim() {
+                "user_agent" => mapping.user_agent = Some(column),
+                "response_time" => mapping.response_time = Some(column),
+                "timestamp" => mapping.timestamp = Some(column),
+                "id" => mapping.id = Some(column),
+                field => return Err(ColumnError::UnknownField(field.to_string())),
+            }
+        }
+
+        Ok(mapping)
+    }
+}
+
+impl Serialize for ColumnMapping {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for ColumnMapping {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(de::Error::custom)
+    }
+}
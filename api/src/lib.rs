@@ -1,3 +1,6 @@
+pub mod access_log;
+pub mod columns;
 pub mod params;
 pub mod requests;
 pub mod responses;
+pub mod validation;
@@ -1,9 +1,18 @@
 use std::fmt;
+use std::str::FromStr;
 
 use chrono::DateTime;
+use chrono::TimeZone;
 use chrono::Utc;
+use serde::de;
 use serde::Deserialize;
+use serde::Deserializer;
 use serde::Serialize;
+use serde::Serializer;
+use uuid::Uuid;
+
+use crate::access_log::AccessLogFormat;
+use crate::columns::ColumnMapping;
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct DateTimeRange {
@@ -32,3 +41,221 @@ impl fmt::Display for DateTimeRange {
         }
     }
 }
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct LogFilter {
+    pub user_agent: Option<String>,
+    #[serde(default)]
+    pub user_agent_match: UserAgentMatch,
+    pub min_response_time: Option<i32>,
+    pub max_response_time: Option<i32>,
+}
+impl LogFilter {
+    pub fn matches(&self, user_agent: &str, response_time: i32) -> bool {
+        let user_agent_matches = match (&self.user_agent, self.user_agent_match) {
+            (None, _) => true,
+            (Some(pattern), UserAgentMatch::Exact) => user_agent == pattern,
+            (Some(pattern), UserAgentMatch::Prefix) => user_agent.starts_with(pattern.as_str()),
+            (Some(pattern), UserAgentMatch::Substring) => user_agent.contains(pattern.as_str()),
+        };
+
+        user_agent_matches
+            && self
+                .min_response_time
+                .map(|min| response_time >= min)
+                .unwrap_or(true)
+            && self
+                .max_response_time
+                .map(|max| response_time <= max)
+                .unwrap_or(true)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UserAgentMatch {
+    #[default]
+    Exact,
+    Prefix,
+    Substring,
+}
+
+/// how uploaded logs are loaded, for csv and bulk json uploads
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct LoadParams {
+    #[serde(default)]
+    pub mode: ImportMode,
+    pub max_errors: Option<usize>,
+    /// chosen by the server from the upload size when not given
+    pub ingest: Option<IngestMethod>,
+}
+impl LoadParams {
+    pub const DEFAULT_MAX_ERRORS: usize = 100;
+
+    /// number of rejected rows reported in detail
+    pub fn max_errors(&self) -> usize {
+        self.max_errors.unwrap_or(Self::DEFAULT_MAX_ERRORS)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct CsvParams {
+    /// `field:column` pairs, the header or the default column order is used when not given
+    #[serde(default)]
+    pub columns: ColumnMapping,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct AccessLogParams {
+    #[serde(default)]
+    pub format: AccessLogFormat,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportMode {
+    /// skip bad rows, and commit rows as they are loaded
+    #[default]
+    Lenient,
+    /// stop at the first bad row, rows before it are kept
+    Strict,
+    /// load the whole file in one transaction, and roll it back on any failure
+    ///
+    /// each file of a multipart upload has its own transaction, so the files before the failed
+    /// one stay committed, and the files after it are not read
+    Atomic,
+}
+impl ImportMode {
+    pub fn stops_on_error(self) -> bool {
+        self != ImportMode::Lenient
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IngestMethod {
+    /// INSERT ... SELECT FROM UNNEST(...) per batch
+    Unnest,
+    /// one COPY ... FROM STDIN for the whole file
+    ///
+    /// the rows are moved to the logs table when the file is finished, so an error of the
+    /// database loses the whole file, even in lenient mode. bad rows are still skipped
+    Copy,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportParams {
+    pub format: ExportFormat,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Parquet,
+    /// arrow ipc file
+    Arrow,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct StatsParams {
+    pub group_by: Option<StatsGroupBy>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StatsGroupBy {
+    UserAgent,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimeseriesParams {
+    pub bucket: Bucket,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Bucket {
+    #[serde(rename = "1m")]
+    OneMinute,
+    #[serde(rename = "5m")]
+    FiveMinutes,
+    #[serde(rename = "1h")]
+    OneHour,
+    #[serde(rename = "1d")]
+    OneDay,
+}
+impl Bucket {
+    pub fn seconds(self) -> i64 {
+        match self {
+            Bucket::OneMinute => 60,
+            Bucket::FiveMinutes => 5 * 60,
+            Bucket::OneHour => 60 * 60,
+            Bucket::OneDay => 24 * 60 * 60,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Pagination {
+    pub limit: Option<u32>,
+    pub cursor: Option<Cursor>,
+}
+impl Pagination {
+    pub const DEFAULT_LIMIT: u32 = 100;
+    pub const MAX_LIMIT: u32 = 1000;
+
+    /// requested page size, clamped to `1..=MAX_LIMIT`
+    pub fn limit(&self) -> u32 {
+        self.limit
+            .unwrap_or(Self::DEFAULT_LIMIT)
+            .clamp(1, Self::MAX_LIMIT)
+    }
+}
+
+/// position of the last log in a page, logs are ordered by `(timestamp, id)`
+///
+/// clients should treat the string form as opaque and just pass it back as `cursor`.
+/// the micros are written as the hex of their bits, so a log before 1970 has a cursor too
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Cursor {
+    pub timestamp: DateTime<Utc>,
+    pub id: Uuid,
+}
+impl fmt::Display for Cursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:x}_{}",
+            self.timestamp.timestamp_micros() as u64,
+            self.id.simple()
+        )
+    }
+}
+impl FromStr for Cursor {
+    type Err = CursorParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (micros, id) = s.split_once('_').ok_or(CursorParseError)?;
+        let micros = u64::from_str_radix(micros, 16).map_err(|_| CursorParseError)? as i64;
+        let timestamp = Utc
+            .timestamp_micros(micros)
+            .single()
+            .ok_or(CursorParseError)?;
+        let id = Uuid::try_parse(id).map_err(|_| CursorParseError)?;
+        Ok(Self { timestamp, id })
+    }
+}
+impl Serialize for Cursor {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+impl<'de> Deserialize<'de> for Cursor {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(de::Error::custom)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, derive_more::Display, derive_more::Error)]
+#[display(fmt = "invalid cursor")]
+pub struct CursorParseError;
@@ -2,10 +2,16 @@ use chrono::DateTime;
 use chrono::Utc;
 use serde::Deserialize;
 use serde::Serialize;
+use uuid::Uuid;
 
+/// a log to be stored
+///
+/// a log with an `id` is stored only once, sending it again is a no-op.
+/// the fields are in csv column order
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct NewLog {
     pub user_agent: String,
     pub response_time: i32,
     pub timestamp: Option<DateTime<Utc>>,
+    pub id: Option<Uuid>,
 }
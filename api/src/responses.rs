@@ -1,2 +1,5 @@
-pub mod csv;
+pub mod health;
+pub mod load;
 pub mod logs;
+pub mod problem;
+pub mod stats;
@@ -0,0 +1,36 @@
+use serde::Deserialize;
+use serde::Serialize;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HealthResponse {
+    pub status: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReadinessResponse {
+    /// the server can take requests
+    pub ready: bool,
+    /// the database answered a query. it isn't asked while the pool is saturated
+    pub database: bool,
+    pub pool: PoolResponse,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PoolResponse {
+    /// open connections
+    pub size: u32,
+    /// open connections that aren't in use
+    pub idle: u32,
+    pub max_connections: u32,
+    /// every connection is in use, and no more can be opened
+    pub saturated: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VersionResponse {
+    pub version: String,
+    /// the commit the server was built from, when it was known
+    pub git_sha: Option<String>,
+    /// the latest migration applied to the database, when it could be read
+    pub migration_version: Option<i64>,
+}
@@ -0,0 +1,27 @@
+use serde::Deserialize;
+use serde::Serialize;
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize, derive_more::Display)]
+#[display(
+    fmt = "Load Response [{} accepted, {} duplicates, {} rejected]",
+    accepted,
+    duplicates,
+    rejected
+)]
+pub struct LoadResponse {
+    pub accepted: u64,
+    /// rows with the id of a stored log, they are skipped
+    #[serde(default)]
+    pub duplicates: u64,
+    pub rejected: u64,
+    /// rejected rows, up to the requested number of errors
+    pub errors: Vec<RowErrorResponse>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RowErrorResponse {
+    /// 1-based line number in the uploaded file, or position in a json array
+    pub line: u64,
+    pub raw: String,
+    pub error: String,
+}
@@ -0,0 +1,21 @@
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::validation::FieldError;
+
+/// RFC 7807 problem details, the body of error responses (`application/problem+json`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProblemResponse {
+    #[serde(rename = "type")]
+    pub problem_type: String,
+    pub title: String,
+    pub status: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    /// also sent as the `X-Request-Id` header, and written to the access log
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    /// fields of the request that are not valid
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
+}
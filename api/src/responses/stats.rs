@@ -0,0 +1,30 @@
+use chrono::DateTime;
+use chrono::Utc;
+use serde::Deserialize;
+use serde::Serialize;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatsResponse {
+    /// only set when grouped by user agent
+    pub user_agent: Option<String>,
+    pub count: i64,
+    pub min: Option<i32>,
+    pub max: Option<i32>,
+    pub mean: Option<f64>,
+    pub p50: Option<f64>,
+    pub p95: Option<f64>,
+    pub p99: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BucketStatsResponse {
+    /// start of the bucket
+    pub bucket: DateTime<Utc>,
+    pub count: i64,
+    pub min: Option<i32>,
+    pub max: Option<i32>,
+    pub mean: Option<f64>,
+    pub p50: Option<f64>,
+    pub p95: Option<f64>,
+    pub p99: Option<f64>,
+}
@@ -0,0 +1,151 @@
+use std::fmt;
+
+use chrono::DateTime;
+use chrono::Duration;
+use chrono::Utc;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::requests::logs::NewLog;
+
+/// limits for `NewLog`, checked on every ingestion path before the logs reach the database
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ValidationRules {
+    max_user_agent_len: usize,
+    max_response_time: Option<i32>,
+    max_future: Duration,
+}
+
+impl ValidationRules {
+    /// length of the `user_agent` column, in characters
+    pub const MAX_USER_AGENT_LEN: usize = 256;
+    /// clocks of clients may be ahead of the server a little
+    pub const DEFAULT_MAX_FUTURE_SECS: i64 = 5 * 60;
+    /// the largest `max_future` that is configured, a year
+    pub const MAX_FUTURE_SECS: i64 = 366 * 24 * 60 * 60;
+
+    /// longer user agents don't fit in the column, so the length is capped at its size
+    pub fn with_max_user_agent_len(self, max_user_agent_len: usize) -> Self {
+        Self {
+            max_user_agent_len: max_user_agent_len.min(Self::MAX_USER_AGENT_LEN),
+            ..self
+        }
+    }
+
+    pub fn with_max_response_time(self, max_response_time: i32) -> Self {
+        Self {
+            max_response_time: Some(max_response_time),
+            ..self
+        }
+    }
+
+    /// how far timestamps may be ahead of the clock of the server
+    pub fn with_max_future(self, max_future: Duration) -> Self {
+        Self { max_future, ..self }
+    }
+
+    pub fn max_user_agent_len(&self) -> usize {
+        self.max_user_agent_len
+    }
+
+    pub fn max_response_time(&self) -> Option<i32> {
+        self.max_response_time
+    }
+
+    pub fn max_future(&self) -> Duration {
+        self.max_future
+    }
+
+    pub fn validate(&self, log: NewLog) -> Result<NewLog, ValidationErrors> {
+        self.validate_at(log, Utc::now())
+    }
+
+    /// the log as it is, or all of the fields that break the rules
+    pub fn validate_at(&self, log: NewLog, now: DateTime<Utc>) -> Result<NewLog, ValidationErrors> {
+        let mut errors = Vec::new();
+
+        if log.user_agent.trim().is_empty() {
+            errors.push(FieldError::new("user_agent", "must not be empty"));
+        } else if log.user_agent.chars().count() > self.max_user_agent_len {
+            errors.push(FieldError::new(
+                "user_agent",
+                format!("must be at most {} characters", self.max_user_agent_len),
+            ));
+        }
+
+        if log.response_time < 0 {
+            errors.push(FieldError::new("response_time", "must not be negative"));
+        } else if let Some(max) = self
+            .max_response_time
+            .filter(|max| log.response_time > *max)
+        {
+            errors.push(FieldError::new(
+                "response_time",
+                format!("must be at most {max}"),
+            ));
+        }
+
+        if let Some(timestamp) = log.timestamp {
+            // no timestamp is too far ahead when `now + max_future` is out of range
+            let latest = now.checked_add_signed(self.max_future);
+            if latest.is_some_and(|latest| timestamp > latest) {
+                errors.push(FieldError::new(
+                    "timestamp",
+                    format!(
+                        "must be at most {} seconds in the future",
+                        self.max_future.num_seconds()
+                    ),
+                ));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(log)
+        } else {
+            Err(ValidationErrors(errors))
+        }
+    }
+}
+
+impl Default for ValidationRules {
+    fn default() -> Self {
+        Self {
+            max_user_agent_len: Self::MAX_USER_AGENT_LEN,
+            max_response_time: None,
+            max_future: Duration::seconds(Self::DEFAULT_MAX_FUTURE_SECS),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    fn new(field: &str, message: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+}
+
+/// fields of a `NewLog` that break the rules, at least one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationErrors(pub Vec<FieldError>);
+
+impl fmt::Display for ValidationErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, e) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, "; ")?;
+            }
+            write!(f, "{}: {}", e.field, e.message)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ValidationErrors {}
@@ -4,6 +4,7 @@ use cli::opts::Command;
 use cli::opts::Opt;
 use cli::requests::get_logs;
 use cli::requests::post_logs;
+use cli::requests::upload_csv;
 use env_logger::Env;
 use error_stack::IntoReport;
 use error_stack::ResultExt;
@@ -16,7 +17,12 @@ fn main() -> error_stack::Result<(), CliError> {
 
     match opt.command {
         Command::Get { format } => get_logs(&opt.server, format)?,
-        Command::Post => post_logs(&opt.server)?,
+        Command::Post {
+            format,
+            columns,
+            rules,
+        } => post_logs(&opt.server, format, &columns, rules.rules())?,
+        Command::Upload { columns, files } => upload_csv(&opt.server, &columns, &files)?,
     }
     Ok(())
 }
@@ -1,3 +1,11 @@
+use std::path::PathBuf;
+
+use chrono::Duration;
+
+use api::access_log::AccessLogFormat;
+use api::columns::ColumnMapping;
+use api::validation::ValidationRules;
+
 #[derive(Debug, clap::Parser)]
 #[command(author, version, about)]
 pub struct Opt {
@@ -12,12 +20,62 @@ pub struct Opt {
 pub enum Command {
     /// get logs
     Get {
-        /// log format [csv, json]
+        /// log format [csv, json, parquet, arrow]
         #[arg(short, long, value_name = "FORMAT", value_enum, default_value_t = LogFormat::Json)]
         format: LogFormat,
     },
     /// post logs, taking input from stdin
-    Post,
+    Post {
+        /// input format [csv, common, combined, nginx]
+        #[arg(short, long, value_name = "FORMAT", value_enum, default_value_t = PostFormat::Csv)]
+        format: PostFormat,
+        /// column mapping of csv, like `user_agent:ua,response_time:latency_ms`
+        /// (a header line is detected and skipped)
+        #[arg(short, long, value_name = "MAPPING", default_value_t)]
+        columns: ColumnMapping,
+        #[command(flatten)]
+        rules: RulesOpt,
+    },
+    /// upload csv files, which may be compressed as .gz, .zst or .bz2
+    Upload {
+        /// column mapping, like `user_agent:ua,response_time:latency_ms`
+        #[arg(short, long, value_name = "MAPPING", default_value_t)]
+        columns: ColumnMapping,
+        /// csv files
+        #[arg(value_name = "FILE", required = true)]
+        files: Vec<PathBuf>,
+    },
+}
+
+/// rules checked before the logs are sent, like the ones of the server
+#[derive(Debug, Clone, clap::Args)]
+pub struct RulesOpt {
+    /// characters of a user agent
+    #[arg(long, value_name = "CHARS", default_value_t = ValidationRules::MAX_USER_AGENT_LEN)]
+    pub max_user_agent_len: usize,
+    /// milliseconds of a response time, not limited when not set
+    #[arg(long, value_name = "MS")]
+    pub max_response_time: Option<i32>,
+    /// seconds that timestamps may be ahead of this machine
+    #[arg(
+        long,
+        value_name = "SECS",
+        default_value_t = ValidationRules::DEFAULT_MAX_FUTURE_SECS,
+        value_parser = clap::value_parser!(i64).range(0..=ValidationRules::MAX_FUTURE_SECS)
+    )]
+    pub max_future_secs: i64,
+}
+
+impl RulesOpt {
+    pub fn rules(&self) -> ValidationRules {
+        let rules = ValidationRules::default()
+            .with_max_user_agent_len(self.max_user_agent_len)
+            .with_max_future(Duration::seconds(self.max_future_secs));
+        match self.max_response_time {
+            Some(max_response_time) => rules.with_max_response_time(max_response_time),
+            None => rules,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum, derive_more::Display)]
@@ -28,4 +86,37 @@ pub enum LogFormat {
     /// json format
     #[display(fmt = "json")]
     Json,
+    /// parquet file
+    #[display(fmt = "parquet")]
+    Parquet,
+    /// arrow ipc file
+    #[display(fmt = "arrow")]
+    Arrow,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum, derive_more::Display)]
+pub enum PostFormat {
+    /// csv format
+    #[display(fmt = "csv")]
+    Csv,
+    /// Common Log Format, followed by the response time in microseconds
+    #[display(fmt = "common")]
+    Common,
+    /// Combined Log Format, followed by the response time in microseconds
+    #[display(fmt = "combined")]
+    Combined,
+    /// nginx combined format, followed by `$request_time` in seconds
+    #[display(fmt = "nginx")]
+    Nginx,
+}
+
+impl PostFormat {
+    pub fn access_log_format(self) -> Option<AccessLogFormat> {
+        match self {
+            PostFormat::Csv => None,
+            PostFormat::Common => Some(AccessLogFormat::Common),
+            PostFormat::Combined => Some(AccessLogFormat::Combined),
+            PostFormat::Nginx => Some(AccessLogFormat::Nginx),
+        }
+    }
 }
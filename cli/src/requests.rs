@@ -1,22 +1,105 @@
+use std::fmt;
 use std::io;
+use std::path::Path;
+use std::path::PathBuf;
 
+use api::access_log::AccessLogFormat;
+use api::columns::ColumnMapping;
+use api::columns::ColumnSelector;
+use api::params::ExportFormat;
+use api::params::ExportParams;
+use api::params::Pagination;
 use api::requests::logs::NewLog;
+use api::responses::load::LoadResponse;
+use api::responses::logs::LogsResponse;
+use api::validation::ValidationRules;
 use error_stack::IntoReport;
 use error_stack::ResultExt;
 
 use crate::errors::CliError;
 use crate::opts::LogFormat;
+use crate::opts::PostFormat;
 
 pub fn get_logs(server: &str, format: LogFormat) -> error_stack::Result<(), CliError> {
+    match format {
+        LogFormat::Json => get_json_logs(server),
+        LogFormat::Csv => get_csv_logs(server),
+        LogFormat::Parquet => export_logs(server, ExportFormat::Parquet),
+        LogFormat::Arrow => export_logs(server, ExportFormat::Arrow),
+    }
+}
+
+// follow `next_cursor` until the last page, and print all logs as one json array
+fn get_json_logs(server: &str) -> error_stack::Result<(), CliError> {
+    let client = reqwest::blocking::Client::default();
+
+    let mut logs = Vec::new();
+    let mut cursor = None;
+    loop {
+        let mut request = client
+            .get(format!("{server}/logs"))
+            .query(&[("limit", Pagination::MAX_LIMIT)]);
+        if let Some(cursor) = cursor {
+            request = request.query(&[("cursor", cursor)]);
+        }
+        let response = request
+            .send()
+            .into_report()
+            .change_context(CliError)?
+            .error_for_status()
+            .into_report()
+            .change_context(CliError)?
+            .json::<LogsResponse>()
+            .into_report()
+            .change_context(CliError)?;
+
+        logs.extend(response.logs);
+        cursor = response.next_cursor.map(|cursor| cursor.to_string());
+        if cursor.is_none() {
+            break;
+        }
+    }
+
+    let stdout = io::stdout().lock();
+    serde_json::to_writer(stdout, &logs)
+        .into_report()
+        .change_context(CliError)?;
+
+    Ok(())
+}
+
+fn get_csv_logs(server: &str) -> error_stack::Result<(), CliError> {
+    let client = reqwest::blocking::Client::default();
+    let mut response = client
+        .get(format!("{server}/logs"))
+        .header(reqwest::header::ACCEPT, "text/csv")
+        .send()
+        .into_report()
+        .change_context(CliError)?
+        .error_for_status()
+        .into_report()
+        .change_context(CliError)?;
+
+    let mut stdout = io::stdout().lock();
+    response
+        .copy_to(&mut stdout)
+        .into_report()
+        .change_context(CliError)?;
+
+    Ok(())
+}
+
+// columnar files are binary, they are written to stdout as they are
+fn export_logs(server: &str, format: ExportFormat) -> error_stack::Result<(), CliError> {
     let client = reqwest::blocking::Client::default();
-    let uri = match format {
-        LogFormat::Json => format!("{server}/logs"),
-        LogFormat::Csv => format!("{server}/csv"),
-    };
     let mut response = client
-        .get(uri)
+        .get(format!("{server}/export"))
+        .query(&ExportParams { format })
         .send()
         .into_report()
+        .change_context(CliError)?
+        .error_for_status()
+        .into_report()
         .change_context(CliError)?;
 
     let mut stdout = io::stdout().lock();
@@ -28,29 +111,229 @@ pub fn get_logs(server: &str, format: LogFormat) -> error_stack::Result<(), CliE
     Ok(())
 }
 
-pub fn post_logs(server: &str) -> error_stack::Result<(), CliError> {
+// logs are sent to `/logs/bulk` as ndjson, `BULK_CHUNK_LOGS` logs per request
+pub fn post_logs(
+    server: &str,
+    format: PostFormat,
+    columns: &ColumnMapping,
+    rules: ValidationRules,
+) -> error_stack::Result<(), CliError> {
+    let mut sender = BulkSender::new(server, rules);
+
+    match format.access_log_format() {
+        None => post_csv_logs(&mut sender, columns)?,
+        Some(format) => post_access_logs(&mut sender, format)?,
+    }
+
+    let total = sender.finish()?;
+    println!("{total}");
+
+    Ok(())
+}
+
+// the first line is skipped when it is a header, and fields are picked as `columns` maps them
+fn post_csv_logs(
+    sender: &mut BulkSender,
+    columns: &ColumnMapping,
+) -> error_stack::Result<(), CliError> {
     let stdin = io::stdin().lock();
-    let new_logs = csv::ReaderBuilder::default()
+    let records = csv::ReaderBuilder::default()
         .has_headers(false)
+        .flexible(true)
         .from_reader(stdin)
-        .into_deserialize::<NewLog>();
+        .into_records();
 
-    let client = reqwest::blocking::Client::default();
-
-    for log in new_logs {
-        match log {
-            Ok(log) => client
-                .post(format!("{server}/logs"))
-                .json(&log)
-                .send()
-                .into_report()
-                .change_context(CliError)?,
+    let mut selector = ColumnSelector::new(columns.clone());
+    for record in records {
+        let record = match record {
+            Ok(record) => record,
             Err(e) => {
-                log::error!("{e:?}");
+                let line = e.position().map(|position| position.line());
+                sender.reject(line.unwrap_or_default(), e);
                 continue;
             }
         };
+        let line = record
+            .position()
+            .map(|position| position.line())
+            .unwrap_or_default();
+        let fields = record.iter().collect::<Vec<_>>();
+
+        let selected = match selector
+            .select(&fields)
+            .into_report()
+            .change_context(CliError)?
+        {
+            Some(selected) => selected,
+            // the header
+            None => continue,
+        };
+
+        match csv::StringRecord::from(selected.to_vec()).deserialize::<NewLog>(None) {
+            Ok(log) => sender.push(line, log)?,
+            Err(e) => sender.reject(line, e),
+        };
     }
 
     Ok(())
 }
+
+// access log lines are parsed here, and sent like csv rows
+fn post_access_logs(
+    sender: &mut BulkSender,
+    format: AccessLogFormat,
+) -> error_stack::Result<(), CliError> {
+    for (text, line) in io::stdin().lines().zip(1..) {
+        let text = text.into_report().change_context(CliError)?;
+        if text.trim().is_empty() {
+            continue;
+        }
+
+        match format.parse(&text) {
+            Ok(log) => sender.push(line, log)?,
+            Err(e) => sender.reject(line, e),
+        }
+    }
+
+    Ok(())
+}
+
+const BULK_CHUNK_LOGS: usize = 1000;
+
+// ndjson bodies of bulk requests, with the input line numbers of their logs
+struct BulkSender {
+    client: reqwest::blocking::Client,
+    url: String,
+    body: Vec<u8>,
+    lines: Vec<u64>,
+    rules: ValidationRules,
+    total: LoadResponse,
+}
+
+impl BulkSender {
+    fn new(server: &str, rules: ValidationRules) -> Self {
+        Self {
+            client: reqwest::blocking::Client::default(),
+            url: format!("{server}/logs/bulk"),
+            body: Vec::new(),
+            lines: Vec::new(),
+            rules,
+            total: LoadResponse::default(),
+        }
+    }
+
+    // logs that the server would reject are reported here, and not sent
+    fn push(&mut self, line: u64, log: NewLog) -> error_stack::Result<(), CliError> {
+        let log = match self.rules.validate(log) {
+            Ok(log) => log,
+            Err(e) => {
+                self.reject(line, e);
+                return Ok(());
+            }
+        };
+
+        serde_json::to_writer(&mut self.body, &log)
+            .into_report()
+            .change_context(CliError)?;
+        self.body.push(b'\n');
+        self.lines.push(line);
+
+        if self.lines.len() >= BULK_CHUNK_LOGS {
+            self.send()?;
+        }
+        Ok(())
+    }
+
+    // a line that can't be sent is counted with the lines that the server rejects
+    fn reject(&mut self, line: u64, error: impl fmt::Display) {
+        log::error!("line {line}: {error}");
+        self.total.rejected += 1;
+    }
+
+    fn send(&mut self) -> error_stack::Result<(), CliError> {
+        let response = self
+            .client
+            .post(&self.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/x-ndjson")
+            .body(std::mem::take(&mut self.body))
+            .send()
+            .into_report()
+            .change_context(CliError)?
+            .error_for_status()
+            .into_report()
+            .change_context(CliError)?
+            .json::<LoadResponse>()
+            .into_report()
+            .change_context(CliError)?;
+
+        // report the lines of the input, not of the request body
+        for e in response.errors {
+            let line = e
+                .line
+                .checked_sub(1)
+                .and_then(|index| self.lines.get(index as usize))
+                .copied()
+                .unwrap_or_default();
+            log::error!("line {line}: {}", e.error);
+        }
+        self.total.accepted += response.accepted;
+        self.total.duplicates += response.duplicates;
+        self.total.rejected += response.rejected;
+        self.lines.clear();
+
+        Ok(())
+    }
+
+    fn finish(mut self) -> error_stack::Result<LoadResponse, CliError> {
+        if !self.lines.is_empty() {
+            self.send()?;
+        }
+        Ok(self.total)
+    }
+}
+
+// send the files as they are, the server decompresses them while loading
+pub fn upload_csv(
+    server: &str,
+    columns: &ColumnMapping,
+    files: &[PathBuf],
+) -> error_stack::Result<(), CliError> {
+    let mut form = reqwest::blocking::multipart::Form::new();
+    for file in files {
+        let part = reqwest::blocking::multipart::Part::file(file)
+            .into_report()
+            .change_context(CliError)?
+            .mime_str(csv_content_type(file))
+            .into_report()
+            .change_context(CliError)?;
+        form = form.part("file", part);
+    }
+
+    let client = reqwest::blocking::Client::default();
+    let response = client
+        .post(format!("{server}/csv"))
+        .query(&[("columns", columns.to_string())])
+        .multipart(form)
+        .send()
+        .into_report()
+        .change_context(CliError)?
+        .json::<LoadResponse>()
+        .into_report()
+        .change_context(CliError)?;
+
+    for e in &response.errors {
+        log::warn!("line {}: {} {}", e.line, e.error, e.raw);
+    }
+    println!("{response}");
+
+    Ok(())
+}
+
+fn csv_content_type(file: &Path) -> &'static str {
+    match file.extension().and_then(|extension| extension.to_str()) {
+        Some("gz") => "application/gzip",
+        Some("zst") => "application/zstd",
+        Some("bz2") => "application/x-bzip2",
+        _ => "text/csv",
+    }
+}
@@ -0,0 +1,17 @@
+-- Add down migration script here
+ALTER TABLE logs RENAME TO logs_partitioned;
+ALTER TABLE logs_partitioned RENAME CONSTRAINT PK_logs TO PK_logs_partitioned;
+
+CREATE TABLE logs (
+    id UUID NOT NULL,
+    user_agent VARCHAR(256) NOT NULL,
+    response_time INT NOT NULL,
+    timestamp TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    CONSTRAINT PK_logs PRIMARY KEY (id)
+);
+
+-- an id may be stored at several timestamps in the partitioned table, the first one is kept
+INSERT INTO logs
+SELECT DISTINCT ON (id) * FROM logs_partitioned ORDER BY id, timestamp;
+-- the partitions are dropped with their parent
+DROP TABLE logs_partitioned;
@@ -0,0 +1,39 @@
+-- Add up migration script here
+-- logs are partitioned by month on timestamp, so the primary key has to include it
+ALTER TABLE logs RENAME TO logs_unpartitioned;
+ALTER TABLE logs_unpartitioned RENAME CONSTRAINT PK_logs TO PK_logs_unpartitioned;
+
+CREATE TABLE logs (
+    id UUID NOT NULL,
+    user_agent VARCHAR(256) NOT NULL,
+    response_time INT NOT NULL,
+    timestamp TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    CONSTRAINT PK_logs PRIMARY KEY (id, timestamp)
+) PARTITION BY RANGE (timestamp);
+
+-- rows of months without a partition, they are moved when the server creates one
+CREATE TABLE logs_default PARTITION OF logs DEFAULT;
+
+-- monthly partitions (in UTC) from the oldest log up to the next month
+DO $$
+DECLARE
+    this_month TIMESTAMP := date_trunc('month', now() AT TIME ZONE 'UTC');
+    month TIMESTAMP;
+BEGIN
+    SELECT date_trunc('month', MIN(timestamp) AT TIME ZONE 'UTC') INTO month FROM logs_unpartitioned;
+    month := LEAST(COALESCE(month, this_month), this_month);
+
+    WHILE month <= this_month + INTERVAL '1 month' LOOP
+        EXECUTE format(
+            'CREATE TABLE %I PARTITION OF logs FOR VALUES FROM (%L) TO (%L)',
+            'logs_' || to_char(month, '"y"YYYY"m"MM'),
+            month AT TIME ZONE 'UTC',
+            (month + INTERVAL '1 month') AT TIME ZONE 'UTC'
+        );
+        month := month + INTERVAL '1 month';
+    END LOOP;
+END
+$$;
+
+INSERT INTO logs SELECT * FROM logs_unpartitioned;
+DROP TABLE logs_unpartitioned;
@@ -0,0 +1,3 @@
+-- Add down migration script here
+DROP INDEX IF EXISTS logs_user_agent_idx;
+DROP INDEX IF EXISTS logs_timestamp_id_idx;
@@ -0,0 +1,7 @@
+-- Add up migration script here
+-- time range scans and keyset pagination, in the (timestamp, id) order of GET /logs
+CREATE INDEX IF NOT EXISTS logs_timestamp_id_idx ON logs (timestamp, id);
+
+-- exact and prefix matches on the user agent.
+-- LIKE 'prefix%' can only use an index with the pattern operator class
+CREATE INDEX IF NOT EXISTS logs_user_agent_idx ON logs (user_agent text_pattern_ops);
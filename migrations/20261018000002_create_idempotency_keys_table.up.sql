@@ -0,0 +1,13 @@
+-- Add up migration script here
+-- responses of POST /logs by Idempotency-Key, replayed when the request is sent again
+CREATE TABLE IF NOT EXISTS idempotency_keys (
+    key VARCHAR(255) NOT NULL,
+    -- NULL while the first request with the key is in progress
+    status SMALLINT,
+    content_type TEXT,
+    body BYTEA,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    CONSTRAINT PK_idempotency_keys PRIMARY KEY (key)
+);
+
+CREATE INDEX IF NOT EXISTS idempotency_keys_created_at_idx ON idempotency_keys (created_at);
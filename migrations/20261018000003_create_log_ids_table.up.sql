@@ -0,0 +1,9 @@
+-- Add up migration script here
+-- the primary key of the partitioned logs table is (id, timestamp), so the ids alone are kept
+-- unique here. a log is inserted only when its id is inserted in the same statement
+CREATE TABLE IF NOT EXISTS log_ids (
+    id UUID NOT NULL,
+    CONSTRAINT PK_log_ids PRIMARY KEY (id)
+);
+
+INSERT INTO log_ids SELECT DISTINCT id FROM logs;
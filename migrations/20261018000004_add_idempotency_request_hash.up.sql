@@ -0,0 +1,4 @@
+-- Add up migration script here
+-- sha-256 of the request that claimed the key, another request with the key is refused.
+-- NULL for the keys stored before, which are replayed to any request
+ALTER TABLE idempotency_keys ADD COLUMN IF NOT EXISTS request_hash BYTEA;
@@ -0,0 +1,93 @@
+// compare the UNNEST and COPY ingestion paths against a real database
+//
+// BENCH_DATABASE_URL=postgres://... cargo bench -p server --bench ingest
+// BENCH_ROWS で件数を変更できる (default 100,000)
+//
+// the logs of the benchmark are deleted afterwards, so it runs only against a database given
+// for it, not the DATABASE_URL of the server
+use std::env;
+use std::io;
+use std::io::Write;
+use std::time::Instant;
+
+use error_stack::IntoReport;
+use error_stack::ResultExt;
+use futures_util::io::AllowStdIo;
+
+use server::db::csv::read_new_logs;
+use server::db::DbTrait;
+use server::errors::AppError;
+use server::states::DbState;
+
+use api::columns::ColumnMapping;
+use api::params::IngestMethod;
+use api::params::LoadParams;
+use api::validation::ValidationRules;
+
+const BENCH_DATABASE_URL: &str = "BENCH_DATABASE_URL";
+// the day of the generated logs
+const BENCH_DAY: &str = "2023-06-01";
+
+#[actix_web::main]
+async fn main() -> error_stack::Result<(), AppError> {
+    dotenv::dotenv().ok();
+
+    let database_url = env::var(BENCH_DATABASE_URL)
+        .into_report()
+        .change_context(AppError)
+        .attach_printable_lazy(|| format!("{BENCH_DATABASE_URL} is required, like DATABASE_URL"))?;
+    let rows = env::var("BENCH_ROWS")
+        .ok()
+        .and_then(|rows| rows.parse().ok())
+        .unwrap_or(100_000);
+
+    let db_state = DbState::new(&database_url).await?;
+    let day = format!("{BENCH_DAY}T00:00:00Z")
+        .parse()
+        .into_report()
+        .change_context(AppError)?;
+
+    let mut csv = tempfile::NamedTempFile::new()
+        .into_report()
+        .change_context(AppError)?;
+    for i in 0..rows {
+        writeln!(
+            csv,
+            "\"bench agent {}\",{},{BENCH_DAY} 00:00:00 UTC",
+            i % 100,
+            i % 1000
+        )
+        .into_report()
+        .change_context(AppError)?;
+    }
+    csv.flush().into_report().change_context(AppError)?;
+
+    for method in [IngestMethod::Unnest, IngestMethod::Copy] {
+        let params = LoadParams {
+            ingest: Some(method),
+            ..Default::default()
+        };
+
+        let file = csv.reopen().into_report().change_context(AppError)?;
+        let reader = AllowStdIo::new(io::BufReader::new(file));
+
+        let start = Instant::now();
+        let new_logs = read_new_logs(
+            reader,
+            &ColumnMapping::default(),
+            ValidationRules::default(),
+        );
+        let report = db_state.load_logs(new_logs, &params, None).await?;
+        let elapsed = start.elapsed();
+
+        println!(
+            "{method:?}: {} rows in {elapsed:?} ({:.0} rows/s)",
+            report.accepted,
+            report.accepted as f64 / elapsed.as_secs_f64()
+        );
+
+        db_state.delete_logs(Some(day), Some(day)).await?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,27 @@
+use std::path::Path;
+use std::process::Command;
+
+// the commit of the build, for `/version`. `GIT_SHA` is used as it is when it's set,
+// like when the source isn't a git checkout
+fn main() {
+    println!("cargo:rerun-if-env-changed=GIT_SHA");
+    // a path that doesn't exist would run this on every build
+    for path in ["../.git/HEAD", "../.git/refs", "../.git/packed-refs"] {
+        if Path::new(path).exists() {
+            println!("cargo:rerun-if-changed={path}");
+        }
+    }
+
+    if std::env::var_os("GIT_SHA").is_some() {
+        return;
+    }
+    let output = Command::new("git")
+        .args(["rev-parse", "--short=12", "HEAD"])
+        .output();
+    if let Ok(output) = output {
+        if output.status.success() {
+            let sha = String::from_utf8_lossy(&output.stdout);
+            println!("cargo:rustc-env=GIT_SHA={}", sha.trim());
+        }
+    }
+}
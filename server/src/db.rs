@@ -1,31 +1,154 @@
-use std::path;
-
 use async_trait::async_trait;
 use chrono::DateTime;
 use chrono::Utc;
+use futures_util::stream::BoxStream;
+use futures_util::Stream;
+use uuid::Uuid;
 
 use crate::errors::AppError;
+use crate::models::health::PoolStatus;
+use crate::models::idempotency::IdempotencyClaim;
+use crate::models::idempotency::StoredResponse;
+use crate::models::load::LoadReport;
+use crate::models::load::ReadError;
+use crate::models::logs::InsertedLog;
 use crate::models::logs::Log;
+use crate::models::stats::BucketStats;
+use crate::models::stats::ResponseTimeStats;
+
+use api::params::Bucket;
+use api::params::Cursor;
+use api::params::LoadParams;
+use api::params::LogFilter;
+use api::params::StatsGroupBy;
+use api::requests::logs::NewLog;
 
+pub mod access_log;
+pub mod batch;
+pub mod copy;
 pub mod csv;
+pub mod health;
+pub mod idempotency;
+pub mod json;
+pub mod lines;
 pub mod logs;
+pub mod partitions;
+pub mod query;
+pub mod stats;
 
 #[async_trait]
 pub trait DbTrait {
+    /// when a log with the `id` is already stored, it is returned instead
     async fn insert_log(
         &self,
         user_agent: &str,
         response_time: i32,
         timestamp: Option<DateTime<Utc>>,
-    ) -> error_stack::Result<Log, AppError>;
+        id: Option<Uuid>,
+    ) -> error_stack::Result<InsertedLog, AppError>;
+
+    async fn get_log(&self, id: Uuid) -> error_stack::Result<Option<Log>, AppError>;
 
+    /// logs ordered by `(timestamp, id)`, starting right after `cursor`
     async fn get_logs(
         &self,
         from: Option<DateTime<Utc>>,
         until: Option<DateTime<Utc>>,
+        filter: &LogFilter,
+        cursor: Option<Cursor>,
+        limit: i64,
     ) -> error_stack::Result<Vec<Log>, AppError>;
 
-    async fn load_file<P>(&self, file_path: P) -> error_stack::Result<u64, AppError>
+    /// row stream version of `get_logs`, for exporting large ranges with constant memory
+    fn stream_logs(
+        &self,
+        from: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+        filter: &LogFilter,
+    ) -> BoxStream<'static, error_stack::Result<Log, AppError>>;
+
+    /// `true` when the log existed
+    async fn delete_log(&self, id: Uuid) -> error_stack::Result<bool, AppError>;
+
+    /// the number of deleted logs
+    async fn delete_logs(
+        &self,
+        from: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+    ) -> error_stack::Result<u64, AppError>;
+
+    /// delete at most `limit` logs older than `before`, for the retention task
+    async fn purge_logs(
+        &self,
+        before: DateTime<Utc>,
+        limit: i64,
+    ) -> error_stack::Result<u64, AppError>;
+
+    /// create the missing monthly partitions from the month of `from` to the month of `until`,
+    /// and return their names
+    async fn create_partitions(
+        &self,
+        from: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> error_stack::Result<Vec<String>, AppError>;
+
+    /// drop the monthly partitions that only hold logs older than `before`,
+    /// and return their names
+    async fn drop_partitions(
+        &self,
+        before: DateTime<Utc>,
+    ) -> error_stack::Result<Vec<String>, AppError>;
+
+    /// reserve `key` for the request hashed to `request_hash`, or find the response to replay
+    async fn claim_idempotency_key(
+        &self,
+        key: &str,
+        request_hash: &[u8],
+    ) -> error_stack::Result<IdempotencyClaim, AppError>;
+
+    /// store the response of the request that claimed `key`
+    async fn complete_idempotency_key(
+        &self,
+        key: &str,
+        response: &StoredResponse,
+    ) -> error_stack::Result<(), AppError>;
+
+    /// forget the claim of a failed request, so that it can be sent again
+    async fn release_idempotency_key(&self, key: &str) -> error_stack::Result<(), AppError>;
+
+    async fn get_stats(
+        &self,
+        from: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+        group_by: Option<StatsGroupBy>,
+    ) -> error_stack::Result<Vec<ResponseTimeStats>, AppError>;
+
+    async fn get_timeseries(
+        &self,
+        from: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+        bucket: Bucket,
+    ) -> error_stack::Result<Vec<BucketStats>, AppError>;
+
+    /// run a trivial query, to tell that the database is reachable
+    async fn ping(&self) -> error_stack::Result<(), AppError>;
+
+    /// connections of the pool, without waiting for one
+    fn pool_status(&self) -> PoolStatus;
+
+    /// the latest migration applied to the database
+    async fn migration_version(&self) -> error_stack::Result<Option<i64>, AppError>;
+
+    /// load uploaded logs in batches, `size_hint` is the expected size of the whole upload
+    ///
+    /// rows that failed to parse come in as `ReadError::Row`, and are counted in the report.
+    /// the other read errors stop the load, and are returned in the error report
+    async fn load_logs<S>(
+        &self,
+        new_logs: S,
+        params: &LoadParams,
+        size_hint: Option<u64>,
+    ) -> error_stack::Result<LoadReport, AppError>
     where
-        P: AsRef<path::Path> + Send;
+        S: Stream<Item = Result<NewLog, ReadError>> + Send;
 }
@@ -0,0 +1,24 @@
+use futures_util::AsyncBufRead;
+use futures_util::Stream;
+
+use crate::db::lines::read_line_logs;
+use crate::models::load::ReadError;
+
+use api::access_log::AccessLogFormat;
+use api::requests::logs::NewLog;
+use api::validation::ValidationRules;
+
+// read `NewLog` from web server access log lines, as the bytes arrive
+pub fn read_access_logs<'r, R>(
+    reader: R,
+    format: AccessLogFormat,
+    rules: ValidationRules,
+) -> impl Stream<Item = Result<NewLog, ReadError>> + Send + 'r
+where
+    R: AsyncBufRead + Unpin + Send + 'r,
+{
+    read_line_logs(reader, move |line| {
+        let log = format.parse(line).map_err(|e| e.to_string())?;
+        rules.validate(log).map_err(|e| e.to_string())
+    })
+}
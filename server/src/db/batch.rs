@@ -0,0 +1,90 @@
+use chrono::DateTime;
+use chrono::SubsecRound;
+use chrono::Utc;
+use uuid::Uuid;
+
+use crate::models::logs::Log;
+
+use api::requests::logs::NewLog;
+
+// buffer of logs to be inserted together
+//
+// ログのデータを列ごとの配列で保持する (bulk_insert_logs の UNNEST にそのまま渡せる)
+// push した行は、clear するまで必ず保持される
+#[derive(Debug, Clone)]
+pub struct LogBatch {
+    capacity: usize,
+    ids: Vec<Uuid>,
+    user_agents: Vec<String>,
+    response_times: Vec<i32>,
+    timestamps: Vec<DateTime<Utc>>,
+}
+
+impl LogBatch {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            capacity,
+            ids: Vec::with_capacity(capacity),
+            user_agents: Vec::with_capacity(capacity),
+            response_times: Vec::with_capacity(capacity),
+            timestamps: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// store a log, and return true when the batch is full and should be flushed
+    pub fn push(&mut self, log: NewLog) -> bool {
+        self.ids.push(log.id.unwrap_or_else(Uuid::new_v4));
+        self.user_agents.push(log.user_agent);
+        self.response_times.push(log.response_time);
+        self.timestamps
+            .push(log.timestamp.unwrap_or_else(|| Utc::now().trunc_subsecs(0)));
+
+        self.is_full()
+    }
+
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.len() >= self.capacity
+    }
+
+    pub fn clear(&mut self) {
+        self.ids.clear();
+        self.user_agents.clear();
+        self.response_times.clear();
+        self.timestamps.clear();
+    }
+
+    pub fn ids(&self) -> &[Uuid] {
+        &self.ids
+    }
+
+    pub fn user_agents(&self) -> &[String] {
+        &self.user_agents
+    }
+
+    pub fn response_times(&self) -> &[i32] {
+        &self.response_times
+    }
+
+    pub fn timestamps(&self) -> &[DateTime<Utc>] {
+        &self.timestamps
+    }
+
+    /// the stored logs as rows
+    pub fn logs(&self) -> impl Iterator<Item = Log> + '_ {
+        (0..self.len()).map(|i| Log {
+            id: self.ids[i],
+            user_agent: self.user_agents[i].clone(),
+            response_time: self.response_times[i],
+            timestamp: self.timestamps[i],
+        })
+    }
+}
@@ -0,0 +1,41 @@
+use chrono::SecondsFormat;
+use error_stack::IntoReport;
+use error_stack::ResultExt;
+
+use crate::db::batch::LogBatch;
+use crate::errors::AppError;
+
+// COPY can't skip conflicting rows, so they are copied to a staging table first
+pub(crate) const CREATE_STAGING: &str = r#"
+    CREATE TEMPORARY TABLE IF NOT EXISTS logs_staging (LIKE logs INCLUDING DEFAULTS)
+"#;
+
+pub(crate) const COPY_LOGS: &str = r#"
+    COPY logs_staging (id, user_agent, response_time, timestamp)
+    FROM STDIN
+    WITH (FORMAT csv)
+"#;
+
+// encode a batch as COPY csv data
+//
+// COPY の csv 形式では、クォートされていない空文字列は NULL として扱われるため、
+// 全ての列をクォートする
+pub(crate) fn copy_rows(batch: &LogBatch) -> error_stack::Result<Vec<u8>, AppError> {
+    let mut w = csv::WriterBuilder::new()
+        .has_headers(false)
+        .quote_style(csv::QuoteStyle::Always)
+        .from_writer(Vec::new());
+
+    for log in batch.logs() {
+        w.write_record([
+            log.id.to_string(),
+            log.user_agent,
+            log.response_time.to_string(),
+            log.timestamp.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+        ])
+        .into_report()
+        .change_context(AppError)?;
+    }
+
+    w.into_inner().into_report().change_context(AppError)
+}
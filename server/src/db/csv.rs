@@ -1 +1,200 @@
+use std::io;
+use std::pin::Pin;
+use std::task::Context;
+use std::task::Poll;
 
+use futures_util::AsyncRead;
+use futures_util::Stream;
+use futures_util::StreamExt;
+
+use crate::db::lines::MAX_LINE_BYTES;
+use crate::models::load::ReadError;
+use crate::models::load::RowError;
+
+use api::columns::ColumnMapping;
+use api::columns::ColumnSelector;
+use api::requests::logs::NewLog;
+use api::validation::ValidationRules;
+
+// read `NewLog` rows from csv, as the bytes arrive
+//
+// the first record is skipped as a header when it names one of the columns, and the fields are
+// picked from the positions given by the header or the column mapping
+//
+// rows that can't be parsed, or break the `rules`, are returned as `ReadError::Row`, with the
+// line number and the raw row. when the columns can't be resolved at all, `ReadError::Columns`
+// is returned before any row, and nothing more is read. a failure of the reader, like a corrupt
+// compressed file, is returned as `ReadError::Body`, and stops the reading too. so does a record
+// longer than `MAX_LINE_BYTES`, as `ReadError::LineTooLong`
+//
+// csv::Position::line lags behind on CRLF line endings, so the line number is taken from the
+// record index instead (it differs only when a quoted field spans lines)
+pub fn read_new_logs<'r, R>(
+    reader: R,
+    columns: &ColumnMapping,
+    rules: ValidationRules,
+) -> impl Stream<Item = Result<NewLog, ReadError>> + Send + 'r
+where
+    R: AsyncRead + Unpin + Send + 'r,
+{
+    let mut selector = ColumnSelector::new(columns.clone());
+    let mut records = csv_async::AsyncReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .create_reader(RecordLimit::new(reader))
+        .into_records();
+
+    async_stream::stream! {
+        while let Some(record) = records.next().await {
+            let record = match record {
+                Ok(record) => record,
+                // the rest can't be read after a broken upload or a corrupt compressed file
+                Err(e) if e.is_io_error() => {
+                    match record_too_long(&e) {
+                        Some(line) => yield Err(ReadError::LineTooLong(line)),
+                        None => yield Err(ReadError::Body(e.to_string())),
+                    }
+                    break;
+                }
+                Err(e) => {
+                    yield Err(ReadError::Row(RowError {
+                        line: e.position().map(line_number).unwrap_or_default(),
+                        raw: String::new(),
+                        error: e.to_string(),
+                    }));
+                    continue;
+                }
+            };
+            let line = record.position().map(line_number).unwrap_or_default();
+            let fields = record.iter().collect::<Vec<_>>();
+
+            let selected = match selector.select(&fields) {
+                Ok(Some(selected)) => selected,
+                // the header
+                Ok(None) => continue,
+                Err(e) => {
+                    yield Err(ReadError::Columns(e));
+                    break;
+                }
+            };
+
+            yield csv_async::StringRecord::from(selected.to_vec())
+                .deserialize::<NewLog>(None)
+                .map_err(|e| e.to_string())
+                .and_then(|log| rules.validate(log).map_err(|e| e.to_string()))
+                .map_err(|error| {
+                    ReadError::Row(RowError {
+                        line,
+                        raw: raw_row(&record),
+                        error,
+                    })
+                });
+        }
+    }
+}
+
+#[derive(Debug, derive_more::Display, derive_more::Error)]
+#[display(fmt = "line {} is too long", _0)]
+struct RecordTooLong(#[error(not(source))] u64);
+
+fn record_too_long(e: &csv_async::Error) -> Option<u64> {
+    match e.kind() {
+        csv_async::ErrorKind::Io(e) => e
+            .get_ref()
+            .and_then(|e| e.downcast_ref::<RecordTooLong>())
+            .map(|e| e.0),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Quoting {
+    FieldStart,
+    Unquoted,
+    Quoted,
+    // a quote in a quoted field, which ends it unless another quote follows
+    QuoteInQuoted,
+}
+
+// fails the read at a record longer than `MAX_LINE_BYTES`
+//
+// csv_async buffers a whole record before returning it, so a quote that is never closed would
+// make it buffer the rest of the upload. the quotes are followed here to tell where records end
+struct RecordLimit<R> {
+    reader: R,
+    line: u64,
+    len: usize,
+    quoting: Quoting,
+}
+
+impl<R> RecordLimit<R> {
+    fn new(reader: R) -> Self {
+        Self {
+            reader,
+            line: 1,
+            len: 0,
+            quoting: Quoting::FieldStart,
+        }
+    }
+
+    fn scan(&mut self, bytes: &[u8]) -> io::Result<()> {
+        for &b in bytes {
+            self.quoting = match (self.quoting, b) {
+                (Quoting::Quoted, b'"') => Quoting::QuoteInQuoted,
+                (Quoting::Quoted, _) => Quoting::Quoted,
+                (Quoting::QuoteInQuoted, b'"') => Quoting::Quoted,
+                (_, b'\n') => {
+                    self.line += 1;
+                    self.len = 0;
+                    self.quoting = Quoting::FieldStart;
+                    continue;
+                }
+                (_, b',') => Quoting::FieldStart,
+                (Quoting::FieldStart, b'"') => Quoting::Quoted,
+                _ => Quoting::Unquoted,
+            };
+            if b != b'\r' {
+                self.len += 1;
+            }
+            if self.len > MAX_LINE_BYTES {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    RecordTooLong(self.line),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for RecordLimit<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let n = match Pin::new(&mut self.reader).poll_read(cx, buf) {
+            Poll::Ready(Ok(n)) => n,
+            poll => return poll,
+        };
+        Poll::Ready(self.scan(&buf[..n]).map(|_| n))
+    }
+}
+
+fn line_number(position: &csv_async::Position) -> u64 {
+    position.record() + 1
+}
+
+// write the record back as a csv line, keeping the quotes
+fn raw_row(record: &csv_async::StringRecord) -> String {
+    let mut w = csv::WriterBuilder::new()
+        .terminator(csv::Terminator::Any(b'\n'))
+        .from_writer(Vec::new());
+
+    w.write_record(record.iter())
+        .ok()
+        .and_then(|_| w.into_inner().ok())
+        .and_then(|raw| String::from_utf8(raw).ok())
+        .map(|raw| raw.trim_end().to_string())
+        .unwrap_or_default()
+}
@@ -0,0 +1,26 @@
+use error_stack::IntoReport;
+use error_stack::ResultExt;
+use sqlx::PgConnection;
+
+use crate::errors::AppError;
+
+pub(crate) async fn ping(conn: &mut PgConnection) -> error_stack::Result<(), AppError> {
+    sqlx::query!("SELECT 1 AS one")
+        .fetch_one(conn)
+        .await
+        .into_report()
+        .change_context(AppError)?;
+
+    Ok(())
+}
+
+// migrations are applied by `sqlx migrate run`, which records them in `_sqlx_migrations`
+pub(crate) async fn migration_version(
+    conn: &mut PgConnection,
+) -> error_stack::Result<Option<i64>, AppError> {
+    sqlx::query_scalar!("SELECT max(version) FROM _sqlx_migrations WHERE success")
+        .fetch_one(conn)
+        .await
+        .into_report()
+        .change_context(AppError)
+}
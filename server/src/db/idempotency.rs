@@ -0,0 +1,137 @@
+use chrono::Duration;
+use chrono::Utc;
+use error_stack::IntoReport;
+use error_stack::ResultExt;
+use sqlx::PgConnection;
+
+use crate::errors::AppError;
+use crate::models::idempotency::IdempotencyClaim;
+use crate::models::idempotency::StoredResponse;
+
+// keys are forgotten after a day, like most APIs with idempotency keys
+const KEY_TTL_HOURS: i64 = 24;
+// a claim is taken over after this, in case the server stopped while processing it
+const IN_PROGRESS_TIMEOUT_MINUTES: i64 = 5;
+
+pub(crate) async fn claim_key(
+    conn: &mut PgConnection,
+    key: &str,
+    request_hash: &[u8],
+) -> error_stack::Result<IdempotencyClaim, AppError> {
+    let now = Utc::now();
+
+    sqlx::query!(
+        "DELETE FROM idempotency_keys WHERE created_at < $1",
+        now - Duration::hours(KEY_TTL_HOURS)
+    )
+    .execute(&mut *conn)
+    .await
+    .into_report()
+    .change_context(AppError)?;
+
+    let claimed = sqlx::query_scalar!(
+        r#"
+        INSERT INTO idempotency_keys (key, request_hash, created_at)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (key) DO UPDATE
+        SET
+            request_hash = EXCLUDED.request_hash,
+            status = NULL,
+            content_type = NULL,
+            body = NULL,
+            created_at = EXCLUDED.created_at
+        WHERE
+            idempotency_keys.status IS NULL
+            AND
+            idempotency_keys.created_at < $4
+        RETURNING key
+        "#,
+        key,
+        request_hash,
+        now,
+        now - Duration::minutes(IN_PROGRESS_TIMEOUT_MINUTES)
+    )
+    .fetch_optional(&mut *conn)
+    .await
+    .into_report()
+    .change_context(AppError)?;
+    if claimed.is_some() {
+        return Ok(IdempotencyClaim::Claimed);
+    }
+
+    let stored = sqlx::query!(
+        "SELECT request_hash, status, content_type, body FROM idempotency_keys WHERE key = $1",
+        key
+    )
+    .fetch_optional(&mut *conn)
+    .await
+    .into_report()
+    .change_context(AppError)?;
+
+    // a key that is removed in the meantime is also treated as in progress
+    let claim = match stored {
+        Some(stored)
+            if stored
+                .request_hash
+                .as_ref()
+                .is_some_and(|stored_hash| stored_hash != request_hash) =>
+        {
+            IdempotencyClaim::Mismatch
+        }
+        Some(stored) => match stored.status {
+            Some(status) => IdempotencyClaim::Completed(StoredResponse {
+                status: status as u16,
+                content_type: stored.content_type,
+                body: stored.body.unwrap_or_default(),
+            }),
+            None => IdempotencyClaim::InProgress,
+        },
+        None => IdempotencyClaim::InProgress,
+    };
+    Ok(claim)
+}
+
+pub(crate) async fn complete_key(
+    conn: &mut PgConnection,
+    key: &str,
+    response: &StoredResponse,
+) -> error_stack::Result<(), AppError> {
+    sqlx::query!(
+        r#"
+        UPDATE
+            idempotency_keys
+        SET
+            status = $2,
+            content_type = $3,
+            body = $4
+        WHERE
+            key = $1
+        "#,
+        key,
+        response.status as i16,
+        response.content_type,
+        response.body
+    )
+    .execute(conn)
+    .await
+    .into_report()
+    .change_context(AppError)?;
+
+    Ok(())
+}
+
+pub(crate) async fn release_key(
+    conn: &mut PgConnection,
+    key: &str,
+) -> error_stack::Result<(), AppError> {
+    sqlx::query!(
+        "DELETE FROM idempotency_keys WHERE key = $1 AND status IS NULL",
+        key
+    )
+    .execute(conn)
+    .await
+    .into_report()
+    .change_context(AppError)?;
+
+    Ok(())
+}
@@ -0,0 +1,54 @@
+use futures_util::AsyncBufRead;
+use futures_util::Stream;
+use serde::Deserialize;
+
+use crate::db::lines::read_line_logs;
+use crate::models::load::ReadError;
+use crate::models::load::RowError;
+
+use api::requests::logs::NewLog;
+use api::validation::ValidationRules;
+
+// read `NewLog` objects from ndjson, one per line, as the bytes arrive
+pub fn read_ndjson_logs<'r, R>(
+    reader: R,
+    rules: ValidationRules,
+) -> impl Stream<Item = Result<NewLog, ReadError>> + Send + 'r
+where
+    R: AsyncBufRead + Unpin + Send + 'r,
+{
+    read_line_logs(reader, move |line| {
+        let log = serde_json::from_str::<NewLog>(line).map_err(|e| e.to_string())?;
+        rules.validate(log).map_err(|e| e.to_string())
+    })
+}
+
+// read `NewLog` objects from a json array
+//
+// a body that is not an array fails as a whole, elements that are not a `NewLog` are returned
+// as `RowError` with their 1-based position
+pub fn read_json_logs(
+    body: &[u8],
+    rules: ValidationRules,
+) -> serde_json::Result<Vec<Result<NewLog, ReadError>>> {
+    let values = serde_json::from_slice::<Vec<serde_json::Value>>(body)?;
+
+    let logs = values
+        .into_iter()
+        .zip(1..)
+        .map(|(value, line)| {
+            NewLog::deserialize(&value)
+                .map_err(|e| e.to_string())
+                .and_then(|log| rules.validate(log).map_err(|e| e.to_string()))
+                .map_err(|error| {
+                    ReadError::Row(RowError {
+                        line,
+                        raw: value.to_string(),
+                        error,
+                    })
+                })
+        })
+        .collect();
+
+    Ok(logs)
+}
@@ -0,0 +1,70 @@
+use futures_util::AsyncBufRead;
+use futures_util::AsyncBufReadExt;
+use futures_util::AsyncReadExt;
+use futures_util::Stream;
+
+use crate::models::load::ReadError;
+use crate::models::load::RowError;
+
+use api::requests::logs::NewLog;
+
+/// longest line, or csv record, that is read. a log is far smaller than this
+pub const MAX_LINE_BYTES: usize = 64 * 1024;
+
+// read one `NewLog` per line with `parse`, as the bytes arrive
+//
+// blank lines are skipped. lines that can't be parsed are returned as `ReadError::Row`.
+// reading stops at a line longer than `MAX_LINE_BYTES`, which is not buffered any further,
+// and when the reader fails
+pub fn read_line_logs<'r, R, F>(
+    mut reader: R,
+    parse: F,
+) -> impl Stream<Item = Result<NewLog, ReadError>> + Send + 'r
+where
+    R: AsyncBufRead + Unpin + Send + 'r,
+    F: Fn(&str) -> Result<NewLog, String> + Send + 'r,
+{
+    async_stream::stream! {
+        let mut line = 0;
+        let mut buf = Vec::new();
+
+        loop {
+            buf.clear();
+            // one byte over the limit, to tell a line of exactly `MAX_LINE_BYTES` from a longer one
+            let limit = MAX_LINE_BYTES as u64 + 1;
+            match (&mut reader).take(limit).read_until(b'\n', &mut buf).await {
+                Ok(0) => break,
+                Ok(_) => {}
+                Err(e) => {
+                    yield Err(ReadError::Body(e.to_string()));
+                    break;
+                }
+            }
+            line += 1;
+
+            let text = buf.strip_suffix(b"\n").unwrap_or(&buf);
+            let text = text.strip_suffix(b"\r").unwrap_or(text);
+            if text.len() > MAX_LINE_BYTES {
+                yield Err(ReadError::LineTooLong(line));
+                break;
+            }
+
+            let raw = match String::from_utf8(text.to_vec()) {
+                Ok(raw) => raw,
+                Err(e) => {
+                    yield Err(ReadError::Row(RowError {
+                        line,
+                        raw: String::from_utf8_lossy(text).into_owned(),
+                        error: e.to_string(),
+                    }));
+                    continue;
+                }
+            };
+            if raw.trim().is_empty() {
+                continue;
+            }
+
+            yield parse(&raw).map_err(|error| ReadError::Row(RowError { line, raw, error }));
+        }
+    }
+}
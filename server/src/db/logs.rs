@@ -1,24 +1,55 @@
-use std::fs;
-use std::io;
-use std::path;
-
 use async_trait::async_trait;
 use chrono::DateTime;
 use chrono::SubsecRound;
 use chrono::Utc;
 use error_stack::IntoReport;
 use error_stack::ResultExt;
-use sqlx::pool::PoolConnection;
-use sqlx::Postgres;
+use futures_util::stream::BoxStream;
+use futures_util::Stream;
+use futures_util::StreamExt;
+use sqlx::postgres::PgCopyIn;
+use sqlx::Connection;
+use sqlx::PgConnection;
+use sqlx::QueryBuilder;
 use uuid::Uuid;
 
+use crate::db::batch::LogBatch;
+use crate::db::copy::copy_rows;
+use crate::db::copy::COPY_LOGS;
+use crate::db::copy::CREATE_STAGING;
+use crate::db::health;
+use crate::db::idempotency;
+use crate::db::partitions;
+use crate::db::partitions::create_partition;
+use crate::db::query::push_select_logs;
+use crate::db::query::push_time_range;
+use crate::db::stats::select_stats;
+use crate::db::stats::select_timeseries;
 use crate::db::DbTrait;
 use crate::errors::AppError;
+use crate::models::health::PoolStatus;
+use crate::models::idempotency::IdempotencyClaim;
+use crate::models::idempotency::StoredResponse;
+use crate::models::load::LoadReport;
+use crate::models::load::ReadError;
+use crate::models::logs::InsertedLog;
 use crate::models::logs::Log;
+use crate::models::stats::BucketStats;
+use crate::models::stats::ResponseTimeStats;
 use crate::states::DbState;
 
+use api::params::Bucket;
+use api::params::Cursor;
+use api::params::ImportMode;
+use api::params::IngestMethod;
+use api::params::LoadParams;
+use api::params::LogFilter;
+use api::params::StatsGroupBy;
 use api::requests::logs::NewLog;
 
+// `insert_log` looks for the stored log again, when it is deleted before it is found
+const INSERT_ATTEMPTS: usize = 3;
+
 #[async_trait]
 impl DbTrait for DbState {
     async fn insert_log(
@@ -26,40 +57,103 @@ impl DbTrait for DbState {
         user_agent: &str,
         response_time: i32,
         timestamp: Option<DateTime<Utc>>,
-    ) -> error_stack::Result<Log, AppError> {
+        id: Option<Uuid>,
+    ) -> error_stack::Result<InsertedLog, AppError> {
         let mut conn = self
             .acquire()
             .await
             .into_report()
             .change_context(AppError)?;
 
-        let id = Uuid::new_v4();
+        let id = id.unwrap_or_else(Uuid::new_v4);
         let timestamp = timestamp.unwrap_or_else(|| Utc::now().trunc_subsecs(0));
 
-        let new_log = sqlx::query_as!(
+        // a log deleted between the two statements frees its id, so the insert is tried again
+        for _ in 0..INSERT_ATTEMPTS {
+            // the log is inserted only when its id is new in `log_ids`, a concurrent insert of
+            // the same id waits for this one to commit
+            let new_log = sqlx::query_as!(
+                Log,
+                r#"
+                WITH claimed AS (
+                    INSERT INTO log_ids (id)
+                    VALUES ($1)
+                    ON CONFLICT DO NOTHING
+                    RETURNING id
+                )
+                INSERT INTO logs (id, user_agent, response_time, timestamp)
+                SELECT id, $2, $3, $4 FROM claimed
+                RETURNING id, user_agent, response_time, timestamp
+                "#,
+                id,
+                user_agent,
+                response_time,
+                timestamp
+            )
+            .fetch_optional(&mut conn)
+            .await
+            .into_report()
+            .change_context(AppError)?;
+            if let Some(new_log) = new_log {
+                return Ok(InsertedLog::New(new_log));
+            }
+
+            // the statement sees the log of the insert that claimed the id, it is committed
+            let stored_log = sqlx::query_as!(
+                Log,
+                "SELECT id, user_agent, response_time, timestamp FROM logs WHERE id = $1",
+                id
+            )
+            .fetch_optional(&mut conn)
+            .await
+            .into_report()
+            .change_context(AppError)?;
+            if let Some(stored_log) = stored_log {
+                return Ok(InsertedLog::Existing(stored_log));
+            }
+        }
+
+        Err(error_stack::Report::new(AppError))
+            .attach_printable_lazy(|| format!("the id {id} is taken, but no log has it"))
+    }
+
+    async fn get_log(&self, id: Uuid) -> error_stack::Result<Option<Log>, AppError> {
+        let mut conn = self
+            .acquire()
+            .await
+            .into_report()
+            .change_context(AppError)?;
+
+        let log = sqlx::query_as!(
             Log,
             r#"
-            INSERT INTO logs (id, user_agent, response_time, timestamp)
-            VALUES ($1, $2, $3, $4)
-            RETURNING id, user_agent, response_time, timestamp
+            SELECT
+                id,
+                user_agent,
+                response_time,
+                timestamp
+            FROM
+                logs
+            WHERE
+                id = $1
             "#,
-            id,
-            user_agent,
-            response_time,
-            timestamp
+            id
         )
-        .fetch_one(&mut conn)
+        .fetch_optional(&mut conn)
         .await
         .into_report()
         .change_context(AppError)?;
 
-        Ok(new_log)
+        Ok(log)
     }
 
     async fn get_logs(
         &self,
         from: Option<DateTime<Utc>>,
         until: Option<DateTime<Utc>>,
+        filter: &LogFilter,
+        cursor: Option<Cursor>,
+        limit: i64,
     ) -> error_stack::Result<Vec<Log>, AppError> {
         let mut conn = self
             .acquire()
@@ -67,110 +161,481 @@ impl DbTrait for DbState {
             .into_report()
             .change_context(AppError)?;
 
-        let logs = sqlx::query_as!(
-            Log,
+        let mut query = QueryBuilder::new("");
+        push_select_logs(&mut query, from, until, filter, cursor, Some(limit));
+
+        let logs = query
+            .build_query_as()
+            .fetch_all(&mut conn)
+            .await
+            .into_report()
+            .change_context(AppError)?;
+
+        Ok(logs)
+    }
+
+    fn stream_logs(
+        &self,
+        from: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+        filter: &LogFilter,
+    ) -> BoxStream<'static, error_stack::Result<Log, AppError>> {
+        // the stream must outlive the request handler, so it holds its own pool handle
+        let pool = self.clone();
+        let filter = filter.clone();
+
+        let logs = async_stream::stream! {
+            let mut query = QueryBuilder::new("");
+            push_select_logs(&mut query, from, until, &filter, None, None);
+
+            let mut rows = query.build_query_as::<Log>().fetch(&*pool);
+            while let Some(log) = rows.next().await {
+                yield log.into_report().change_context(AppError);
+            }
+        };
+
+        logs.boxed()
+    }
+
+    async fn delete_log(&self, id: Uuid) -> error_stack::Result<bool, AppError> {
+        let mut conn = self
+            .acquire()
+            .await
+            .into_report()
+            .change_context(AppError)?;
+
+        let result = sqlx::query!(
             r#"
-            SELECT
-                id,
-                user_agent,
-                response_time,
-                timestamp
-            FROM
-                logs
-            WHERE
-                timestamp >= COALESCE($1, timestamp)
-                AND
-                timestamp <= COALESCE($2, timestamp)
+            WITH deleted AS (
+                DELETE FROM logs WHERE id = $1 RETURNING id
+            )
+            DELETE FROM log_ids WHERE id IN (SELECT id FROM deleted)
             "#,
-            from,
-            until
+            id
         )
-        .fetch_all(&mut conn)
+        .execute(&mut conn)
         .await
         .into_report()
         .change_context(AppError)?;
 
-        Ok(logs)
+        Ok(result.rows_affected() > 0)
     }
 
-    async fn load_file<P>(&self, file_path: P) -> error_stack::Result<u64, AppError>
-    where
-        P: AsRef<path::Path> + Send,
-    {
+    async fn delete_logs(
+        &self,
+        from: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+    ) -> error_stack::Result<u64, AppError> {
         let mut conn = self
             .acquire()
             .await
             .into_report()
             .change_context(AppError)?;
 
-        let mut line_count = 0;
+        // the ids of the deleted logs are freed too
+        let mut query = QueryBuilder::new("WITH deleted AS (DELETE FROM logs");
+        push_time_range(&mut query, from, until);
+        query.push(" RETURNING id) DELETE FROM log_ids WHERE id IN (SELECT id FROM deleted)");
 
-        let file = fs::File::open(file_path)
+        let result = query
+            .build()
+            .execute(&mut conn)
+            .await
             .into_report()
             .change_context(AppError)?;
-        let reader = io::BufReader::new(file);
 
-        let logs_iter = csv::ReaderBuilder::new()
-            .has_headers(false)
-            .from_reader(reader)
-            .into_deserialize::<NewLog>();
+        Ok(result.rows_affected())
+    }
 
-        let chunk_size = 1000;
+    async fn purge_logs(
+        &self,
+        before: DateTime<Utc>,
+        limit: i64,
+    ) -> error_stack::Result<u64, AppError> {
+        let mut conn = self
+            .acquire()
+            .await
+            .into_report()
+            .change_context(AppError)?;
 
-        // log data
-        let mut id_vec = Vec::with_capacity(chunk_size);
-        let mut user_agent_vec = Vec::with_capacity(chunk_size);
-        let mut response_time_vec = Vec::with_capacity(chunk_size);
-        let mut timestamp_vec = Vec::with_capacity(chunk_size);
+        // postgres has no DELETE ... LIMIT.
+        // rows are picked by the primary key, an id alone may match logs that are not expired
+        let result = sqlx::query!(
+            r#"
+            WITH deleted AS (
+                DELETE FROM
+                    logs
+                WHERE
+                    (id, timestamp) IN (
+                        SELECT id, timestamp FROM logs WHERE timestamp < $1 LIMIT $2
+                    )
+                RETURNING
+                    id
+            )
+            DELETE FROM log_ids WHERE id IN (SELECT id FROM deleted)
+            "#,
+            before,
+            limit
+        )
+        .execute(&mut conn)
+        .await
+        .into_report()
+        .change_context(AppError)?;
 
-        for (index, log) in logs_iter.enumerate() {
-            if log.is_err() {
-                // skip error rows
-                log::debug!("csv error: {:?}", log.err());
-                continue;
+        Ok(result.rows_affected())
+    }
+
+    async fn create_partitions(
+        &self,
+        from: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> error_stack::Result<Vec<String>, AppError> {
+        let mut created = Vec::new();
+
+        // one transaction per partition, so logs_default is not locked for the whole run
+        for month in partitions::partition_months(from, until) {
+            let mut tx = self.begin().await.into_report().change_context(AppError)?;
+            if create_partition(&mut tx, month).await? {
+                created.push(partitions::partition_name(month));
             }
-            let log = log.unwrap();
-
-            // itertools::chunks が非同期処理に対応していないので、
-            // 時前で 1000 件づつ処理する
-            if index % chunk_size == chunk_size - 1 {
-                // update logs table
-                line_count += bulk_insert_logs(
-                    &mut conn,
-                    &id_vec,
-                    &user_agent_vec,
-                    &response_time_vec,
-                    &timestamp_vec,
-                )
-                .await?;
+            tx.commit().await.into_report().change_context(AppError)?;
+        }
+
+        Ok(created)
+    }
+
+    async fn drop_partitions(
+        &self,
+        before: DateTime<Utc>,
+    ) -> error_stack::Result<Vec<String>, AppError> {
+        let mut tx = self.begin().await.into_report().change_context(AppError)?;
+        let dropped = partitions::drop_partitions(&mut tx, before).await?;
+        tx.commit().await.into_report().change_context(AppError)?;
+
+        Ok(dropped)
+    }
+
+    async fn claim_idempotency_key(
+        &self,
+        key: &str,
+        request_hash: &[u8],
+    ) -> error_stack::Result<IdempotencyClaim, AppError> {
+        let mut conn = self
+            .acquire()
+            .await
+            .into_report()
+            .change_context(AppError)?;
+
+        idempotency::claim_key(&mut conn, key, request_hash).await
+    }
+
+    async fn complete_idempotency_key(
+        &self,
+        key: &str,
+        response: &StoredResponse,
+    ) -> error_stack::Result<(), AppError> {
+        let mut conn = self
+            .acquire()
+            .await
+            .into_report()
+            .change_context(AppError)?;
+
+        idempotency::complete_key(&mut conn, key, response).await
+    }
+
+    async fn release_idempotency_key(&self, key: &str) -> error_stack::Result<(), AppError> {
+        let mut conn = self
+            .acquire()
+            .await
+            .into_report()
+            .change_context(AppError)?;
+
+        idempotency::release_key(&mut conn, key).await
+    }
+
+    async fn get_stats(
+        &self,
+        from: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+        group_by: Option<StatsGroupBy>,
+    ) -> error_stack::Result<Vec<ResponseTimeStats>, AppError> {
+        let mut conn = self
+            .acquire()
+            .await
+            .into_report()
+            .change_context(AppError)?;
+
+        select_stats(&mut conn, from, until, group_by).await
+    }
+
+    async fn get_timeseries(
+        &self,
+        from: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+        bucket: Bucket,
+    ) -> error_stack::Result<Vec<BucketStats>, AppError> {
+        let mut conn = self
+            .acquire()
+            .await
+            .into_report()
+            .change_context(AppError)?;
+
+        select_timeseries(&mut conn, from, until, bucket).await
+    }
+
+    async fn ping(&self) -> error_stack::Result<(), AppError> {
+        let mut conn = self
+            .acquire()
+            .await
+            .into_report()
+            .change_context(AppError)?;
 
-                // clear data
-                id_vec.clear();
-                user_agent_vec.clear();
-                response_time_vec.clear();
-                timestamp_vec.clear();
+        health::ping(&mut conn).await
+    }
+
+    fn pool_status(&self) -> PoolStatus {
+        PoolStatus {
+            size: self.size(),
+            idle: self.num_idle() as u32,
+            max_connections: self.max_connections(),
+        }
+    }
+
+    async fn migration_version(&self) -> error_stack::Result<Option<i64>, AppError> {
+        let mut conn = self
+            .acquire()
+            .await
+            .into_report()
+            .change_context(AppError)?;
+
+        health::migration_version(&mut conn).await
+    }
+
+    async fn load_logs<S>(
+        &self,
+        new_logs: S,
+        params: &LoadParams,
+        size_hint: Option<u64>,
+    ) -> error_stack::Result<LoadReport, AppError>
+    where
+        S: Stream<Item = Result<NewLog, ReadError>> + Send,
+    {
+        // large uploads go through COPY unless the client chose the method
+        let method = params.ingest.unwrap_or(
+            if size_hint.is_some_and(|size| size >= self.copy_threshold()) {
+                IngestMethod::Copy
             } else {
-                // store log data
-                id_vec.push(Uuid::new_v4());
-                user_agent_vec.push(log.user_agent);
-                response_time_vec.push(log.response_time);
-                timestamp_vec.push(log.timestamp.unwrap_or_else(|| Utc::now().trunc_subsecs(0)));
+                IngestMethod::Unnest
+            },
+        );
+        log::debug!("loading logs ({size_hint:?} bytes) by {method:?}");
+
+        let mut conn = self
+            .acquire()
+            .await
+            .into_report()
+            .change_context(AppError)?;
+
+        let report = if params.mode == ImportMode::Atomic {
+            write_logs_atomically(&mut conn, method, new_logs, params, self.batch_size()).await
+        } else {
+            write_logs(&mut conn, method, new_logs, params, self.batch_size()).await
+        };
+
+        // sqlx 0.6 doesn't read all of the responses to a failed COPY, so its connection is
+        // closed instead of going back to the pool out of sync
+        if report.is_err() && method == IngestMethod::Copy {
+            let _ = conn.detach();
+        }
+
+        report
+    }
+}
+
+// atomic: dropping the transaction on an error also rolls it back
+async fn write_logs_atomically<S>(
+    conn: &mut PgConnection,
+    method: IngestMethod,
+    new_logs: S,
+    params: &LoadParams,
+    batch_size: usize,
+) -> error_stack::Result<LoadReport, AppError>
+where
+    S: Stream<Item = Result<NewLog, ReadError>> + Send,
+{
+    let mut tx = conn.begin().await.into_report().change_context(AppError)?;
+
+    let mut report = write_logs(&mut tx, method, new_logs, params, batch_size).await?;
+
+    if report.rejected > 0 {
+        tx.rollback().await.into_report().change_context(AppError)?;
+        report.accepted = 0;
+        report.duplicates = 0;
+    } else {
+        tx.commit().await.into_report().change_context(AppError)?;
+    }
+
+    Ok(report)
+}
+
+// write uploaded rows into the logs table in chunks
+//
+// the connection may be a plain one or a transaction, the caller decides when rows are
+// committed. rows with the id of a stored log are counted as duplicates
+async fn write_logs<S>(
+    conn: &mut PgConnection,
+    method: IngestMethod,
+    new_logs: S,
+    params: &LoadParams,
+    batch_size: usize,
+) -> error_stack::Result<LoadReport, AppError>
+where
+    S: Stream<Item = Result<NewLog, ReadError>> + Send,
+{
+    let mut report = LoadReport::new(params.max_errors());
+
+    let mut writer = LogWriter::new(&mut *conn, method).await?;
+    // a COPY is aborted on errors, dropping it would leave the connection in the COPY state
+    match write_rows(&mut writer, new_logs, params, batch_size, &mut report).await {
+        Ok(()) => writer.finish().await?,
+        Err(e) => {
+            writer.abort().await;
+            return Err(e);
+        }
+    }
+
+    // copied rows are staged, the ones that are not duplicates are moved to the logs table
+    if method == IngestMethod::Copy {
+        let staged = report.accepted;
+        report.accepted = insert_staged_logs(conn).await?;
+        report.duplicates += staged - report.accepted;
+    }
+
+    Ok(report)
+}
+
+// pass the rows to the writer in batches, and count the rejected ones
+async fn write_rows<S>(
+    writer: &mut LogWriter<'_>,
+    new_logs: S,
+    params: &LoadParams,
+    batch_size: usize,
+    report: &mut LoadReport,
+) -> error_stack::Result<(), AppError>
+where
+    S: Stream<Item = Result<NewLog, ReadError>> + Send,
+{
+    let mut batch = LogBatch::new(batch_size);
+
+    futures_util::pin_mut!(new_logs);
+
+    while let Some(log) = new_logs.next().await {
+        let log = match log {
+            Ok(log) => log,
+            Err(ReadError::Row(e)) => {
+                log::debug!("row error: {e:?}");
+                report.reject(e);
+                if params.mode.stops_on_error() {
+                    break;
+                }
+                // skip error rows
+                continue;
             }
+            Err(e) => return Err(error_stack::Report::new(e).change_context(AppError)),
+        };
+
+        // LogBatch に貯めて batch_size 件づつ書き込む
+        if batch.push(log) {
+            writer.write(&batch, report).await?;
+            batch.clear();
         }
+    }
 
-        // upload remaining logs
-        if !id_vec.is_empty() {
-            line_count += bulk_insert_logs(
-                &mut conn,
-                &id_vec,
-                &user_agent_vec,
-                &response_time_vec,
-                &timestamp_vec,
-            )
-            .await?;
+    // upload remaining logs
+    if !batch.is_empty() {
+        writer.write(&batch, report).await?;
+    }
+
+    Ok(())
+}
+
+// destination of loaded logs
+//
+// Unnest inserts each batch with its own INSERT statement,
+// Copy streams all batches into a single COPY statement to the staging table
+enum LogWriter<'c> {
+    Unnest(&'c mut PgConnection),
+    Copy(PgCopyIn<&'c mut PgConnection>),
+}
+
+impl<'c> LogWriter<'c> {
+    async fn new(
+        conn: &'c mut PgConnection,
+        method: IngestMethod,
+    ) -> error_stack::Result<LogWriter<'c>, AppError> {
+        let writer = match method {
+            IngestMethod::Unnest => LogWriter::Unnest(conn),
+            IngestMethod::Copy => {
+                // the staging table is kept by the connection, it may have rows of a failed load
+                for statement in [CREATE_STAGING, "TRUNCATE logs_staging"] {
+                    sqlx::query(statement)
+                        .execute(&mut *conn)
+                        .await
+                        .into_report()
+                        .change_context(AppError)?;
+                }
+
+                let copy_in = conn
+                    .copy_in_raw(COPY_LOGS)
+                    .await
+                    .into_report()
+                    .change_context(AppError)?;
+                LogWriter::Copy(copy_in)
+            }
+        };
+        Ok(writer)
+    }
+
+    async fn write(
+        &mut self,
+        batch: &LogBatch,
+        report: &mut LoadReport,
+    ) -> error_stack::Result<(), AppError> {
+        let written = match self {
+            LogWriter::Unnest(conn) => bulk_insert_logs(conn, batch).await?,
+            LogWriter::Copy(copy_in) => {
+                copy_in
+                    .send(copy_rows(batch)?)
+                    .await
+                    .into_report()
+                    .change_context(AppError)?;
+                batch.len() as u64
+            }
+        };
+
+        report.accepted += written;
+        report.duplicates += batch.len() as u64 - written;
+        Ok(())
+    }
+
+    async fn finish(self) -> error_stack::Result<(), AppError> {
+        if let LogWriter::Copy(copy_in) = self {
+            copy_in
+                .finish()
+                .await
+                .into_report()
+                .change_context(AppError)?;
         }
+        Ok(())
+    }
 
-        Ok(line_count)
+    // the error that stopped the load is returned, so a failed abort is only logged
+    async fn abort(self) {
+        if let LogWriter::Copy(copy_in) = self {
+            if let Err(e) = copy_in.abort("the load of logs failed").await {
+                log::warn!("failed to abort COPY: {e}");
+            }
+        }
     }
 }
 
@@ -205,14 +670,31 @@ impl DbTrait for DbState {
 // と名前を付けている、という▼意味
 //
 async fn bulk_insert_logs(
-    conn: &mut PoolConnection<Postgres>,
-    id_vec: &[Uuid],
-    user_agent_vec: &[String],
-    response_time_vec: &[i32],
-    timestamp_vec: &[DateTime<Utc>],
+    conn: &mut PgConnection,
+    batch: &LogBatch,
 ) -> error_stack::Result<u64, AppError> {
+    // the first log of each id in the batch, and only the ids that are new in `log_ids`.
+    // ids are claimed in order, so that two batches sharing ids don't wait for each other
     let n = sqlx::query!(
                     r#"
+                    WITH new_logs AS (
+                        SELECT DISTINCT ON (id)
+                            id,
+                            user_agent,
+                            response_time,
+                            timestamp
+                        FROM
+                            UNNEST($1::UUID[], $2::TEXT[], $3::INT[], $4::TIMESTAMP WITH TIME ZONE[])
+                                WITH ORDINALITY AS a(id, user_agent, response_time, timestamp, n)
+                        ORDER BY
+                            id, n
+                    ),
+                    claimed AS (
+                        INSERT INTO log_ids (id)
+                        SELECT id FROM new_logs ORDER BY id
+                        ON CONFLICT DO NOTHING
+                        RETURNING id
+                    )
                     INSERT INTO logs (
                         id,
                         user_agent,
@@ -225,12 +707,13 @@ async fn bulk_insert_logs(
                         response_time,
                         timestamp
                     FROM
-                        UNNEST($1::UUID[], $2::TEXT[], $3::INT[], $4::TIMESTAMP WITH TIME ZONE[]) AS a(id, user_agent, response_time, timestamp)
+                        new_logs
+                        JOIN claimed USING (id)
                     "#,
-                    id_vec,
-                    user_agent_vec,
-                    response_time_vec,
-                    timestamp_vec
+                    batch.ids(),
+                    batch.user_agents(),
+                    batch.response_times(),
+                    batch.timestamps()
                 )
                 .execute(conn)
                 .await
@@ -239,3 +722,57 @@ async fn bulk_insert_logs(
 
     Ok(n.rows_affected())
 }
+
+// move the staged rows of a COPY load to the logs table, skipping the stored ids
+//
+// the staging table is temporary, so the statements are not checked at compile time
+async fn insert_staged_logs(conn: &mut PgConnection) -> error_stack::Result<u64, AppError> {
+    // like `bulk_insert_logs`, the first copied row of each id
+    let n = sqlx::query(
+        r#"
+        WITH new_logs AS (
+            SELECT DISTINCT ON (id)
+                id,
+                user_agent,
+                response_time,
+                timestamp
+            FROM
+                logs_staging
+            ORDER BY
+                id, ctid
+        ),
+        claimed AS (
+            INSERT INTO log_ids (id)
+            SELECT id FROM new_logs ORDER BY id
+            ON CONFLICT DO NOTHING
+            RETURNING id
+        )
+        INSERT INTO logs (
+            id,
+            user_agent,
+            response_time,
+            timestamp
+        )
+        SELECT
+            id,
+            user_agent,
+            response_time,
+            timestamp
+        FROM
+            new_logs
+            JOIN claimed USING (id)
+        "#,
+    )
+    .execute(&mut *conn)
+    .await
+    .into_report()
+    .change_context(AppError)?;
+
+    sqlx::query("TRUNCATE logs_staging")
+        .execute(conn)
+        .await
+        .into_report()
+        .change_context(AppError)?;
+
+    Ok(n.rows_affected())
+}
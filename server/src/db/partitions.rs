@@ -0,0 +1,171 @@
+use chrono::DateTime;
+use chrono::Datelike;
+use chrono::Months;
+use chrono::TimeZone;
+use chrono::Utc;
+use error_stack::IntoReport;
+use error_stack::ResultExt;
+use sqlx::PgConnection;
+
+use crate::errors::AppError;
+
+// the logs table is partitioned by month (in UTC), partitions are named `logs_yYYYYmMM`.
+// logs of months without a partition go to `logs_default`
+const PARTITION_PREFIX: &str = "logs_y";
+
+// serializes partition changes between servers sharing the database
+const PARTITIONS_LOCK: i64 = 0x6c6f67735f706172;
+
+/// the first instant of the month of `timestamp`
+pub fn month_start(timestamp: DateTime<Utc>) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(timestamp.year(), timestamp.month(), 1, 0, 0, 0)
+        .unwrap()
+}
+
+pub fn next_month(month: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    month.checked_add_months(Months::new(1))
+}
+
+/// the months from `from` to `until`, both included
+pub fn partition_months(from: DateTime<Utc>, until: DateTime<Utc>) -> Vec<DateTime<Utc>> {
+    let mut months = Vec::new();
+    let mut month = Some(month_start(from));
+    while let Some(current) = month.filter(|month| *month <= until) {
+        months.push(current);
+        month = next_month(current);
+    }
+    months
+}
+
+pub fn partition_name(month: DateTime<Utc>) -> String {
+    format!("{PARTITION_PREFIX}{:04}m{:02}", month.year(), month.month())
+}
+
+/// the month of a partition, `None` for other tables
+pub fn partition_month(name: &str) -> Option<DateTime<Utc>> {
+    let (year, month) = name.strip_prefix(PARTITION_PREFIX)?.split_once('m')?;
+    if year.len() != 4 || month.len() != 2 {
+        return None;
+    }
+    Utc.with_ymd_and_hms(year.parse().ok()?, month.parse().ok()?, 1, 0, 0, 0)
+        .single()
+}
+
+// create the partition of `month` if it is missing, and move its logs out of `logs_default`
+pub(crate) async fn create_partition(
+    conn: &mut PgConnection,
+    month: DateTime<Utc>,
+) -> error_stack::Result<bool, AppError> {
+    let Some(until) = next_month(month) else {
+        return Ok(false);
+    };
+    let name = partition_name(month);
+
+    lock_partitions(conn).await?;
+
+    let exists = sqlx::query_scalar!(r#"SELECT to_regclass($1) IS NOT NULL AS "exists!""#, name)
+        .fetch_one(&mut *conn)
+        .await
+        .into_report()
+        .change_context(AppError)?;
+    if exists {
+        return Ok(false);
+    }
+
+    // attaching a partition fails while `logs_default` has rows of its range,
+    // so they are moved to the new table first. the lock keeps logs from being
+    // inserted into `logs_default` after the move, until the partition is attached
+    let statements = [
+        format!("CREATE TABLE {name} (LIKE logs INCLUDING DEFAULTS)"),
+        "LOCK TABLE logs_default IN SHARE ROW EXCLUSIVE MODE".to_string(),
+        format!(
+            r#"
+            WITH moved AS (
+                DELETE FROM logs_default
+                WHERE timestamp >= '{month}' AND timestamp < '{until}'
+                RETURNING *
+            )
+            INSERT INTO {name} SELECT * FROM moved
+            "#,
+            month = month.to_rfc3339(),
+            until = until.to_rfc3339(),
+        ),
+        format!(
+            "ALTER TABLE logs ATTACH PARTITION {name} FOR VALUES FROM ('{month}') TO ('{until}')",
+            month = month.to_rfc3339(),
+            until = until.to_rfc3339(),
+        ),
+    ];
+    for statement in statements {
+        sqlx::query(&statement)
+            .execute(&mut *conn)
+            .await
+            .into_report()
+            .change_context(AppError)?;
+    }
+
+    Ok(true)
+}
+
+// drop the partitions of the months that end before `before`
+pub(crate) async fn drop_partitions(
+    conn: &mut PgConnection,
+    before: DateTime<Utc>,
+) -> error_stack::Result<Vec<String>, AppError> {
+    lock_partitions(conn).await?;
+
+    let names = sqlx::query_scalar!(
+        r#"
+        SELECT
+            c.relname::TEXT AS "name!"
+        FROM
+            pg_inherits i
+            JOIN pg_class c ON c.oid = i.inhrelid
+        WHERE
+            i.inhparent = 'logs'::REGCLASS
+        ORDER BY
+            c.relname
+        "#
+    )
+    .fetch_all(&mut *conn)
+    .await
+    .into_report()
+    .change_context(AppError)?;
+
+    let mut dropped = Vec::new();
+    for name in names {
+        let expired = partition_month(&name)
+            .and_then(next_month)
+            .is_some_and(|until| until <= before);
+        if !expired {
+            continue;
+        }
+
+        // the ids of the dropped logs are freed too
+        let statements = [
+            format!("DELETE FROM log_ids WHERE id IN (SELECT id FROM {name})"),
+            format!("DROP TABLE {name}"),
+        ];
+        for statement in statements {
+            sqlx::query(&statement)
+                .execute(&mut *conn)
+                .await
+                .into_report()
+                .change_context(AppError)?;
+        }
+        dropped.push(name);
+    }
+
+    Ok(dropped)
+}
+
+// held until the end of the transaction
+async fn lock_partitions(conn: &mut PgConnection) -> error_stack::Result<(), AppError> {
+    sqlx::query!("SELECT pg_advisory_xact_lock($1)", PARTITIONS_LOCK)
+        .execute(conn)
+        .await
+        .into_report()
+        .change_context(AppError)?;
+
+    Ok(())
+}
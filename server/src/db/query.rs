@@ -0,0 +1,93 @@
+use chrono::DateTime;
+use chrono::Utc;
+use sqlx::Postgres;
+use sqlx::QueryBuilder;
+
+use api::params::Cursor;
+use api::params::LogFilter;
+use api::params::UserAgentMatch;
+
+// predicates are only written for the bounds that are given.
+// `timestamp >= COALESCE($1, timestamp)` can't use an index, even when `$1` is set
+
+/// `SELECT` logs in `(timestamp, id)` order, starting right after `cursor`
+pub fn push_select_logs(
+    query: &mut QueryBuilder<'_, Postgres>,
+    from: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+    filter: &LogFilter,
+    cursor: Option<Cursor>,
+    limit: Option<i64>,
+) {
+    query.push(
+        r#"
+        SELECT
+            id,
+            user_agent,
+            response_time,
+            timestamp
+        FROM
+            logs
+        "#,
+    );
+    push_time_range(query, from, until);
+    push_log_filter(query, filter);
+    if let Some(cursor) = cursor {
+        query
+            .push(" AND (timestamp, id) > (")
+            .push_bind(cursor.timestamp)
+            .push(", ")
+            .push_bind(cursor.id)
+            .push(")");
+    }
+    query.push(" ORDER BY timestamp, id");
+    if let Some(limit) = limit {
+        query.push(" LIMIT ").push_bind(limit);
+    }
+}
+
+/// `WHERE` clause for the time range, other predicates follow with `AND`
+pub(crate) fn push_time_range(
+    query: &mut QueryBuilder<'_, Postgres>,
+    from: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+) {
+    query.push(" WHERE TRUE");
+    if let Some(from) = from {
+        query.push(" AND timestamp >= ").push_bind(from);
+    }
+    if let Some(until) = until {
+        query.push(" AND timestamp <= ").push_bind(until);
+    }
+}
+
+pub(crate) fn push_log_filter(query: &mut QueryBuilder<'_, Postgres>, filter: &LogFilter) {
+    if let Some(user_agent) = &filter.user_agent {
+        let escaped = user_agent
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+
+        match filter.user_agent_match {
+            UserAgentMatch::Exact => query
+                .push(" AND user_agent = ")
+                .push_bind(user_agent.clone()),
+            UserAgentMatch::Prefix => query
+                .push(" AND user_agent LIKE ")
+                .push_bind(format!("{escaped}%")),
+            UserAgentMatch::Substring => query
+                .push(" AND user_agent LIKE ")
+                .push_bind(format!("%{escaped}%")),
+        };
+    }
+    if let Some(min_response_time) = filter.min_response_time {
+        query
+            .push(" AND response_time >= ")
+            .push_bind(min_response_time);
+    }
+    if let Some(max_response_time) = filter.max_response_time {
+        query
+            .push(" AND response_time <= ")
+            .push_bind(max_response_time);
+    }
+}
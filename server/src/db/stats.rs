@@ -0,0 +1,96 @@
+use chrono::DateTime;
+use chrono::Utc;
+use error_stack::IntoReport;
+use error_stack::ResultExt;
+use sqlx::PgConnection;
+use sqlx::QueryBuilder;
+
+use crate::db::query::push_time_range;
+use crate::errors::AppError;
+use crate::models::stats::BucketStats;
+use crate::models::stats::ResponseTimeStats;
+
+use api::params::Bucket;
+use api::params::StatsGroupBy;
+
+const AGGREGATES: &str = r#"
+            COUNT(*) AS count,
+            MIN(response_time) AS min,
+            MAX(response_time) AS max,
+            AVG(response_time)::FLOAT8 AS mean,
+            percentile_cont(0.5) WITHIN GROUP (ORDER BY response_time) AS p50,
+            percentile_cont(0.95) WITHIN GROUP (ORDER BY response_time) AS p95,
+            percentile_cont(0.99) WITHIN GROUP (ORDER BY response_time) AS p99
+"#;
+
+// response time statistics
+//
+// percentile_cont は指定した割合の位置の値を、前後の値から線形補間して返す
+// 集計対象が 0 件の場合、グループ化しなければ count = 0 の 1 行、
+// user agent でグループ化すると 0 行になる
+pub(crate) async fn select_stats(
+    conn: &mut PgConnection,
+    from: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+    group_by: Option<StatsGroupBy>,
+) -> error_stack::Result<Vec<ResponseTimeStats>, AppError> {
+    let user_agent = match group_by {
+        None => "NULL::TEXT",
+        Some(StatsGroupBy::UserAgent) => "user_agent",
+    };
+
+    let mut query = QueryBuilder::new(format!(
+        r#"
+        SELECT
+            {user_agent} AS user_agent,
+            {AGGREGATES}
+        FROM
+            logs
+        "#
+    ));
+    push_time_range(&mut query, from, until);
+    if group_by.is_some() {
+        query.push(" GROUP BY user_agent ORDER BY user_agent");
+    }
+
+    query
+        .build_query_as()
+        .fetch_all(conn)
+        .await
+        .into_report()
+        .change_context(AppError)
+}
+
+// response time statistics per time bucket
+//
+// date_bin は timestamp を起点 (epoch) から bucket 幅ごとに切り捨てる
+// ログが 1 件も無い bucket は返さない
+pub(crate) async fn select_timeseries(
+    conn: &mut PgConnection,
+    from: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+    bucket: Bucket,
+) -> error_stack::Result<Vec<BucketStats>, AppError> {
+    // the bucket width is one of the `Bucket` variants, not a user supplied string
+    let mut query = QueryBuilder::new(format!(
+        r#"
+        SELECT
+            date_bin(make_interval(secs => {seconds}), timestamp, TIMESTAMP WITH TIME ZONE 'epoch') AS bucket,
+            {AGGREGATES}
+        FROM
+            logs
+        "#,
+        seconds = bucket.seconds()
+    ));
+    push_time_range(&mut query, from, until);
+    query.push(" GROUP BY 1 ORDER BY 1");
+
+    let stats = query
+        .build_query_as()
+        .fetch_all(conn)
+        .await
+        .into_report()
+        .change_context(AppError)?;
+
+    Ok(stats)
+}
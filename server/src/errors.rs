@@ -1,26 +1,173 @@
+use actix_web::http;
+use actix_web::http::StatusCode;
+use actix_web::HttpResponse;
+use actix_web::ResponseError;
+
+use crate::models::load::ReadError;
+
+use api::responses::problem::ProblemResponse;
+use api::validation::ValidationErrors;
+
+pub const PROBLEM_JSON: &str = "application/problem+json";
+
 #[derive(Debug, derive_more::Display)]
 #[display(fmt = "Application Error")]
 pub struct AppError;
 
 impl error_stack::Context for AppError {}
 
-#[derive(Debug, derive_more::Display, derive_more::Error, derive_more::From)]
-
+/// errors of the handlers, their messages are sent to the client as the `detail` of a problem
+#[derive(Debug, derive_more::Display, derive_more::Error)]
 pub enum AppResponseError {
-    #[display(fmt = "Multipart Error {0}", _0)]
-    MultiPartError(#[error(source)] actix_multipart::MultipartError),
-    #[display(fmt = "Other Response Error")]
-    Other,
+    /// the request is malformed: bad json, query parameters, multipart bodies...
+    #[display(fmt = "{}", _0)]
+    Validation(#[error(not(source))] String),
+    /// the request is well-formed, but the log breaks the validation rules
+    #[display(fmt = "invalid log: {}", _0)]
+    Unprocessable(#[error(not(source))] ValidationErrors),
+    #[display(fmt = "unsupported media type `{}`", _0)]
+    UnsupportedMediaType(#[error(not(source))] String),
+    #[display(fmt = "no acceptable media type in `{}`", _0)]
+    NotAcceptable(#[error(not(source))] String),
+    #[display(fmt = "not found")]
+    NotFound,
+    #[display(fmt = "{}", _0)]
+    Conflict(#[error(not(source))] String),
+    /// the `Idempotency-Key` was sent before with another request
+    #[display(fmt = "the idempotency key `{}` was used for another request", _0)]
+    IdempotencyKeyReused(#[error(not(source))] String),
+    #[display(fmt = "the request body is too large")]
+    PayloadTooLarge,
+    /// the database can't be reached, the request can be sent again later
+    #[display(fmt = "the service is unavailable")]
+    Unavailable,
+    #[display(fmt = "internal error")]
+    Internal,
 }
 
-impl actix_web::ResponseError for AppResponseError {}
+impl ResponseError for AppResponseError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Self::Validation(_) => StatusCode::BAD_REQUEST,
+            Self::Unprocessable(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            Self::UnsupportedMediaType(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            Self::NotAcceptable(_) => StatusCode::NOT_ACCEPTABLE,
+            Self::NotFound => StatusCode::NOT_FOUND,
+            Self::Conflict(_) => StatusCode::CONFLICT,
+            Self::IdempotencyKeyReused(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            Self::PayloadTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+            Self::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+            Self::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        problem_response(self.problem())
+    }
+}
+
+impl AppResponseError {
+    /// problem details without the request id, which is added by the middleware
+    pub fn problem(&self) -> ProblemResponse {
+        let mut problem = problem(self.status_code(), Some(self.to_string()));
+        if let Self::Unprocessable(errors) = self {
+            problem.errors = errors.0.clone();
+        }
+        problem
+    }
+}
+
+/// RFC 7807 problem details, for the errors of the handlers and of actix-web
+pub fn problem(status: StatusCode, detail: Option<String>) -> ProblemResponse {
+    ProblemResponse {
+        problem_type: "about:blank".into(),
+        title: status.canonical_reason().unwrap_or_default().into(),
+        status: status.as_u16(),
+        detail,
+        request_id: None,
+        errors: Vec::new(),
+    }
+}
+
+pub fn problem_response(problem: ProblemResponse) -> HttpResponse {
+    let status = StatusCode::from_u16(problem.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    let mut response = HttpResponse::build(status);
+    if status == StatusCode::SERVICE_UNAVAILABLE {
+        response.insert_header((http::header::RETRY_AFTER, "5"));
+    }
+    response.content_type(PROBLEM_JSON).json(problem)
+}
+
+impl From<actix_multipart::MultipartError> for AppResponseError {
+    fn from(e: actix_multipart::MultipartError) -> Self {
+        match e {
+            actix_multipart::MultipartError::Payload(e) => e.into(),
+            e => Self::Validation(e.to_string()),
+        }
+    }
+}
+
+impl From<actix_web::error::PayloadError> for AppResponseError {
+    fn from(e: actix_web::error::PayloadError) -> Self {
+        match e {
+            actix_web::error::PayloadError::Overflow => Self::PayloadTooLarge,
+            e => Self::Validation(e.to_string()),
+        }
+    }
+}
+
+impl From<ValidationErrors> for AppResponseError {
+    fn from(errors: ValidationErrors) -> Self {
+        Self::Unprocessable(errors)
+    }
+}
+
+impl From<&ReadError> for AppResponseError {
+    fn from(e: &ReadError) -> Self {
+        match e {
+            ReadError::LineTooLong(_) => Self::PayloadTooLarge,
+            e => Self::Validation(e.to_string()),
+        }
+    }
+}
+
+impl From<serde_json::Error> for AppResponseError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Validation(e.to_string())
+    }
+}
 
 impl<C> From<error_stack::Report<C>> for AppResponseError
 where
     C: error_stack::Context,
 {
     fn from(report: error_stack::Report<C>) -> Self {
+        // uploads that can't be read are errors of the client
+        if let Some(e) = report.downcast_ref::<ReadError>() {
+            log::debug!("{report:?}");
+            return Self::from(e);
+        }
+
         log::error!("{report:?}");
-        Self::Other
+        match report.downcast_ref::<sqlx::Error>() {
+            Some(e) if is_unavailable(e) => Self::Unavailable,
+            _ => Self::Internal,
+        }
+    }
+}
+
+// lost connections and a full pool, not errors of the queries
+fn is_unavailable(e: &sqlx::Error) -> bool {
+    match e {
+        sqlx::Error::Io(_)
+        | sqlx::Error::Tls(_)
+        | sqlx::Error::PoolTimedOut
+        | sqlx::Error::PoolClosed => true,
+        // connection exceptions, and the server shutting down or starting up
+        sqlx::Error::Database(e) => e
+            .code()
+            .map(|code| code.starts_with("08") || code.starts_with("57P"))
+            .unwrap_or(false),
+        _ => false,
     }
 }
@@ -1,5 +1,9 @@
+pub mod config;
 pub mod db;
 pub mod errors;
 pub mod models;
+pub mod partitions;
+pub mod request_id;
+pub mod retention;
 pub mod scopes;
 pub mod states;
@@ -1,51 +1,93 @@
-use std::env;
-use std::net;
-
 use actix_web::middleware;
 use actix_web::web;
 use actix_web::App;
 use actix_web::HttpServer;
-use env_logger::Env;
+use clap::Parser;
 use error_stack::IntoReport;
 use error_stack::ResultExt;
 
+use server::config::Opts;
 use server::errors::AppError;
+use server::request_id::request_id;
 use server::scopes::csv::csv_scope;
+use server::scopes::export::export_scope;
+use server::scopes::health::health_scope;
+use server::scopes::ingest::ingest_scope;
 use server::scopes::logs::logs_scope;
+use server::scopes::stats::stats_scope;
 use server::states::DbState;
 
+// the default format of `Logger`, with the request id of the response
+const ACCESS_LOG_FORMAT: &str =
+    r#"%a "%r" %s %b "%{Referer}i" "%{User-Agent}i" %T %{x-request-id}o"#;
+
 #[actix_web::main]
 async fn main() -> error_stack::Result<(), AppError> {
-    dotenv::dotenv().into_report().change_context(AppError)?;
-    env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
-
-    let addr = net::SocketAddr::from(([127, 0, 0, 1], 3000));
-    log::info!("Listening on {addr:?}");
+    // the environment variables may come from a .env file, which is optional
+    dotenv::dotenv().ok();
+    let opts = Opts::parse();
+    let config = opts.config().change_context(AppError)?;
+    if opts.print_config {
+        print!("{}", config.to_toml().change_context(AppError)?);
+        return Ok(());
+    }
+    env_logger::Builder::new()
+        .parse_filters(&config.log.level)
+        .init();
 
-    let database_url = env::var("DATABASE_URL")
-        .into_report()
-        .change_context(AppError)?;
-    let db_state = DbState::new(&database_url).await?;
+    let db_state = DbState::connect(
+        &config.database.url,
+        config.database.max_connections,
+        config.database.pool_options(),
+    )
+    .await?
+    .with_batch_size(config.database.batch_size)
+    .with_copy_threshold(config.database.copy_threshold);
+    config.partitions.policy().spawn(db_state.clone());
+    if let Some(retention) = config.retention.policy() {
+        log::info!(
+            "Purging logs older than {} days",
+            config.retention.days.unwrap_or_default()
+        );
+        retention.spawn(db_state.clone());
+    }
     let app_state = web::Data::new(db_state);
+    let limits = config.uploads.limits();
+    let rules = config.validation.rules();
 
-    HttpServer::new(move || {
+    let addr = (config.server.host, config.server.port);
+    log::info!("Listening on {addr:?}");
+
+    let mut server = HttpServer::new(move || {
         App::new()
-            .wrap(middleware::Logger::default())
+            .wrap(middleware::from_fn(request_id))
+            .wrap(middleware::Logger::new(ACCESS_LOG_FORMAT))
             .wrap(middleware::NormalizePath::new(
                 middleware::TrailingSlash::Trim,
             ))
             .wrap(middleware::Compress::default())
             .app_data(app_state.clone())
+            .app_data(limits)
+            .app_data(rules)
             .configure(csv_scope::<DbState>)
+            .configure(export_scope::<DbState>)
+            .configure(health_scope::<DbState>)
+            .configure(ingest_scope::<DbState>)
             .configure(logs_scope::<DbState>)
-    })
-    .bind(addr)
-    .into_report()
-    .change_context(AppError)?
-    .run()
-    .await
-    .into_report()
-    .change_context(AppError)?;
+            .configure(stats_scope::<DbState>)
+    });
+    if let Some(workers) = config.server.workers {
+        server = server.workers(workers);
+    }
+
+    server
+        .bind(addr)
+        .into_report()
+        .change_context(AppError)?
+        .run()
+        .await
+        .into_report()
+        .change_context(AppError)?;
 
     Ok(())
 }
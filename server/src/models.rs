@@ -1 +1,5 @@
+pub mod health;
+pub mod idempotency;
+pub mod load;
 pub mod logs;
+pub mod stats;
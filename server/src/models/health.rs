@@ -0,0 +1,27 @@
+use api::responses::health::PoolResponse;
+
+/// connections of the database pool
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolStatus {
+    pub size: u32,
+    pub idle: u32,
+    pub max_connections: u32,
+}
+
+impl PoolStatus {
+    /// a request would have to wait for a connection
+    pub fn is_saturated(&self) -> bool {
+        self.idle == 0 && self.size >= self.max_connections
+    }
+}
+
+impl From<PoolStatus> for PoolResponse {
+    fn from(status: PoolStatus) -> Self {
+        PoolResponse {
+            size: status.size,
+            idle: status.idle,
+            max_connections: status.max_connections,
+            saturated: status.is_saturated(),
+        }
+    }
+}
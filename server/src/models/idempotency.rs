@@ -0,0 +1,18 @@
+/// response of a request with an `Idempotency-Key`, replayed for the same key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredResponse {
+    pub status: u16,
+    pub content_type: Option<String>,
+    pub body: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IdempotencyClaim {
+    /// the key is new, the request is to be processed
+    Claimed,
+    /// the first request with the key is not finished yet
+    InProgress,
+    Completed(StoredResponse),
+    /// the key was claimed by a request with another method, path or body
+    Mismatch,
+}
@@ -0,0 +1,91 @@
+use api::columns::ColumnError;
+use api::responses::load::LoadResponse;
+use api::responses::load::RowErrorResponse;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoadReport {
+    pub accepted: u64,
+    pub duplicates: u64,
+    pub rejected: u64,
+    pub errors: Vec<RowError>,
+    max_errors: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RowError {
+    pub line: u64,
+    pub raw: String,
+    pub error: String,
+}
+
+/// errors of the readers of uploads, a bad row is skipped, the others stop the upload
+#[derive(Debug, Clone, PartialEq, Eq, derive_more::Display)]
+pub enum ReadError {
+    #[display(fmt = "line {}: {}", "_0.line", "_0.error")]
+    Row(RowError),
+    /// the csv columns can't be found, no row of the file can be read
+    #[display(fmt = "{}", _0)]
+    Columns(ColumnError),
+    /// the body can't be read any further, like a corrupt or truncated compressed file
+    #[display(fmt = "the upload can't be read: {}", _0)]
+    Body(String),
+    /// a line of a line based upload is too long to be a log
+    #[display(fmt = "line {} is too long", _0)]
+    LineTooLong(u64),
+}
+
+impl error_stack::Context for ReadError {}
+
+impl LoadReport {
+    pub fn new(max_errors: usize) -> Self {
+        Self {
+            accepted: 0,
+            duplicates: 0,
+            rejected: 0,
+            errors: Vec::new(),
+            max_errors,
+        }
+    }
+
+    /// count a rejected row, keeping its details while under the error cap
+    pub fn reject(&mut self, error: RowError) {
+        self.rejected += 1;
+        if self.errors.len() < self.max_errors {
+            self.errors.push(error);
+        }
+    }
+
+    pub fn merge(&mut self, other: LoadReport) {
+        self.accepted += other.accepted;
+        self.duplicates += other.duplicates;
+        self.rejected += other.rejected;
+
+        let room = self.max_errors.saturating_sub(self.errors.len());
+        self.errors.extend(other.errors.into_iter().take(room));
+    }
+}
+
+impl From<LoadReport> for LoadResponse {
+    fn from(report: LoadReport) -> Self {
+        LoadResponse {
+            accepted: report.accepted,
+            duplicates: report.duplicates,
+            rejected: report.rejected,
+            errors: report
+                .errors
+                .into_iter()
+                .map(RowErrorResponse::from)
+                .collect(),
+        }
+    }
+}
+
+impl From<RowError> for RowErrorResponse {
+    fn from(error: RowError) -> Self {
+        RowErrorResponse {
+            line: error.line,
+            raw: error.raw,
+            error: error.error,
+        }
+    }
+}
@@ -3,6 +3,7 @@ use chrono::Utc;
 use sqlx::FromRow;
 use uuid::Uuid;
 
+use api::params::Cursor;
 use api::responses::logs::LogResponse;
 
 #[derive(Debug, Clone, PartialEq, Eq, FromRow)]
@@ -13,12 +14,38 @@ pub struct Log {
     pub timestamp: DateTime<Utc>,
 }
 
+impl Log {
+    pub fn cursor(&self) -> Cursor {
+        Cursor {
+            timestamp: self.timestamp,
+            id: self.id,
+        }
+    }
+}
+
+/// the log of `insert_log`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InsertedLog {
+    New(Log),
+    /// a log with the id was already stored, nothing is inserted
+    Existing(Log),
+}
+
+impl InsertedLog {
+    pub fn into_log(self) -> Log {
+        match self {
+            Self::New(log) | Self::Existing(log) => log,
+        }
+    }
+}
+
 impl From<Log> for LogResponse {
     fn from(log: Log) -> Self {
         LogResponse {
             user_agent: log.user_agent,
             response_time: log.response_time,
             timestamp: log.timestamp,
+            id: log.id,
         }
     }
 }
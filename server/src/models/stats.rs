@@ -0,0 +1,112 @@
+use chrono::DateTime;
+use chrono::Utc;
+use sqlx::FromRow;
+
+use api::responses::stats::BucketStatsResponse;
+use api::responses::stats::StatsResponse;
+
+#[derive(Debug, Clone, PartialEq, FromRow)]
+pub struct ResponseTimeStats {
+    pub user_agent: Option<String>,
+    pub count: i64,
+    pub min: Option<i32>,
+    pub max: Option<i32>,
+    pub mean: Option<f64>,
+    pub p50: Option<f64>,
+    pub p95: Option<f64>,
+    pub p99: Option<f64>,
+}
+
+impl ResponseTimeStats {
+    /// same statistics as the SQL aggregation, computed in memory
+    pub fn from_response_times(user_agent: Option<String>, mut response_times: Vec<i32>) -> Self {
+        response_times.sort_unstable();
+
+        let count = response_times.len() as i64;
+        let mean = (count > 0)
+            .then(|| response_times.iter().map(|&t| f64::from(t)).sum::<f64>() / count as f64);
+
+        Self {
+            user_agent,
+            count,
+            min: response_times.first().copied(),
+            max: response_times.last().copied(),
+            mean,
+            p50: percentile_cont(&response_times, 0.5),
+            p95: percentile_cont(&response_times, 0.95),
+            p99: percentile_cont(&response_times, 0.99),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, FromRow)]
+pub struct BucketStats {
+    pub bucket: DateTime<Utc>,
+    pub count: i64,
+    pub min: Option<i32>,
+    pub max: Option<i32>,
+    pub mean: Option<f64>,
+    pub p50: Option<f64>,
+    pub p95: Option<f64>,
+    pub p99: Option<f64>,
+}
+
+impl BucketStats {
+    pub fn from_response_times(bucket: DateTime<Utc>, response_times: Vec<i32>) -> Self {
+        let stats = ResponseTimeStats::from_response_times(None, response_times);
+
+        Self {
+            bucket,
+            count: stats.count,
+            min: stats.min,
+            max: stats.max,
+            mean: stats.mean,
+            p50: stats.p50,
+            p95: stats.p95,
+            p99: stats.p99,
+        }
+    }
+}
+
+// Postgres の percentile_cont と同じく、前後の値を線形補間する
+fn percentile_cont(sorted: &[i32], fraction: f64) -> Option<f64> {
+    let last = sorted.len().checked_sub(1)?;
+
+    let position = fraction * last as f64;
+    let lower = position.floor() as usize;
+    let upper = position.ceil() as usize;
+
+    let lower_value = f64::from(sorted[lower]);
+    let upper_value = f64::from(sorted[upper]);
+    Some(lower_value + (upper_value - lower_value) * (position - lower as f64))
+}
+
+impl From<ResponseTimeStats> for StatsResponse {
+    fn from(stats: ResponseTimeStats) -> Self {
+        StatsResponse {
+            user_agent: stats.user_agent,
+            count: stats.count,
+            min: stats.min,
+            max: stats.max,
+            mean: stats.mean,
+            p50: stats.p50,
+            p95: stats.p95,
+            p99: stats.p99,
+        }
+    }
+}
+
+impl From<BucketStats> for BucketStatsResponse {
+    fn from(stats: BucketStats) -> Self {
+        BucketStatsResponse {
+            bucket: stats.bucket,
+            count: stats.count,
+            min: stats.min,
+            max: stats.max,
+            mean: stats.mean,
+            p50: stats.p50,
+            p95: stats.p95,
+            p99: stats.p99,
+        }
+    }
+}
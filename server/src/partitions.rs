@@ -0,0 +1,73 @@
+use std::time;
+
+use actix_web::rt;
+use chrono::DateTime;
+use chrono::Months;
+use chrono::Utc;
+
+use crate::db::DbTrait;
+use crate::errors::AppError;
+
+/// monthly partitions are created `months_ahead` of time, checked every `interval`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PartitionPolicy {
+    months_ahead: u32,
+    interval: time::Duration,
+}
+
+impl Default for PartitionPolicy {
+    fn default() -> Self {
+        Self {
+            months_ahead: Self::DEFAULT_MONTHS_AHEAD,
+            interval: Self::DEFAULT_INTERVAL,
+        }
+    }
+}
+
+impl PartitionPolicy {
+    pub const DEFAULT_MONTHS_AHEAD: u32 = 2;
+    pub const DEFAULT_INTERVAL: time::Duration = time::Duration::from_secs(60 * 60);
+
+    pub fn with_months_ahead(self, months_ahead: u32) -> Self {
+        Self {
+            months_ahead,
+            ..self
+        }
+    }
+
+    pub fn with_interval(self, interval: time::Duration) -> Self {
+        Self { interval, ..self }
+    }
+
+    /// create the partitions from the month of `now` to `months_ahead`
+    pub async fn create<DB: DbTrait>(
+        &self,
+        db: &DB,
+        now: DateTime<Utc>,
+    ) -> error_stack::Result<Vec<String>, AppError> {
+        let until = now
+            .checked_add_months(Months::new(self.months_ahead))
+            .unwrap_or(now);
+
+        db.create_partitions(now, until).await
+    }
+
+    /// run `create` now and periodically in the background, as long as the actix runtime is
+    /// running
+    pub fn spawn<DB: DbTrait + 'static>(self, db: DB) -> rt::task::JoinHandle<()> {
+        rt::spawn(async move {
+            let mut interval = rt::time::interval(self.interval);
+            loop {
+                interval.tick().await;
+                match self.create(&db, Utc::now()).await {
+                    Ok(created) => {
+                        for partition in created {
+                            log::info!("created partition {partition}");
+                        }
+                    }
+                    Err(report) => log::error!("{report:?}"),
+                }
+            }
+        })
+    }
+}
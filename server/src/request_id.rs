@@ -0,0 +1,63 @@
+use actix_web::body::EitherBody;
+use actix_web::body::MessageBody;
+use actix_web::dev::ServiceRequest;
+use actix_web::dev::ServiceResponse;
+use actix_web::http::header::HeaderName;
+use actix_web::http::header::HeaderValue;
+use actix_web::middleware::Next;
+use actix_web::HttpMessage;
+use uuid::Uuid;
+
+use crate::errors::problem;
+use crate::errors::problem_response;
+use crate::errors::AppResponseError;
+
+pub const REQUEST_ID: &str = "x-request-id";
+const REQUEST_ID_MAX_LEN: usize = 255;
+
+/// id of the request, from the `X-Request-Id` header of the client or a new uuid
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestId(pub String);
+
+/// tag the request and the response with a request id, and render errors as problems with it
+///
+/// the errors of extractors (query strings, json bodies...) are also rendered as problems here
+pub async fn request_id(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, actix_web::Error> {
+    let id = req
+        .headers()
+        .get(REQUEST_ID)
+        .and_then(|id| id.to_str().ok())
+        .filter(|id| (1..=REQUEST_ID_MAX_LEN).contains(&id.len()))
+        .filter(|id| id.bytes().all(|b| b.is_ascii_graphic()))
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+    req.extensions_mut().insert(RequestId(id.clone()));
+
+    let res = next.call(req).await?;
+    let problem = res.response().error().map(|e| {
+        let mut problem = match e.as_error::<AppResponseError>() {
+            Some(e) => e.problem(),
+            None => {
+                // messages of server errors that aren't ours may tell about the internals
+                let status = e.as_response_error().status_code();
+                problem(status, status.is_client_error().then(|| e.to_string()))
+            }
+        };
+        problem.request_id = Some(id.clone());
+        problem_response(problem)
+    });
+    let mut res = match problem {
+        Some(problem) => res.into_response(problem).map_into_right_body(),
+        None => res.map_into_left_body(),
+    };
+
+    // the id was checked to be visible ascii
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        res.headers_mut()
+            .insert(HeaderName::from_static(REQUEST_ID), value);
+    }
+    Ok(res)
+}
@@ -0,0 +1,86 @@
+use std::num::NonZeroU32;
+use std::time;
+
+use actix_web::rt;
+use chrono::DateTime;
+use chrono::Duration;
+use chrono::Utc;
+
+use crate::db::DbTrait;
+use crate::errors::AppError;
+
+/// logs older than `max_age` are purged every `interval`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    max_age: Duration,
+    interval: time::Duration,
+    batch_size: NonZeroU32,
+}
+
+impl RetentionPolicy {
+    pub const DEFAULT_INTERVAL: time::Duration = time::Duration::from_secs(60 * 60);
+    /// rows deleted by one statement, so that a purge doesn't lock the table for long
+    pub const DEFAULT_BATCH_SIZE: NonZeroU32 = match NonZeroU32::new(10_000) {
+        Some(batch_size) => batch_size,
+        None => unreachable!(),
+    };
+
+    pub fn new(max_age: Duration) -> Self {
+        Self {
+            max_age,
+            interval: Self::DEFAULT_INTERVAL,
+            batch_size: Self::DEFAULT_BATCH_SIZE,
+        }
+    }
+
+    pub fn with_interval(self, interval: time::Duration) -> Self {
+        Self { interval, ..self }
+    }
+
+    /// a batch has at least one row, or a purge would never end
+    pub fn with_batch_size(self, batch_size: NonZeroU32) -> Self {
+        Self { batch_size, ..self }
+    }
+
+    /// delete the logs that are expired at `now`
+    ///
+    /// whole monthly partitions are dropped, and the rest is deleted batch by batch
+    pub async fn purge<DB: DbTrait>(
+        &self,
+        db: &DB,
+        now: DateTime<Utc>,
+    ) -> error_stack::Result<u64, AppError> {
+        let Some(before) = now.checked_sub_signed(self.max_age) else {
+            return Ok(0);
+        };
+
+        for partition in db.drop_partitions(before).await? {
+            log::info!("dropped partition {partition}");
+        }
+
+        let batch_size = self.batch_size.get();
+        let mut purged = 0;
+        loop {
+            let deleted = db.purge_logs(before, batch_size.into()).await?;
+            purged += deleted;
+            if deleted < batch_size.into() {
+                return Ok(purged);
+            }
+        }
+    }
+
+    /// run `purge` periodically in the background, as long as the actix runtime is running
+    pub fn spawn<DB: DbTrait + 'static>(self, db: DB) -> rt::task::JoinHandle<()> {
+        rt::spawn(async move {
+            let mut interval = rt::time::interval(self.interval);
+            loop {
+                interval.tick().await;
+                match self.purge(&db, Utc::now()).await {
+                    Ok(0) => {}
+                    Ok(purged) => log::info!("purged {purged} expired logs"),
+                    Err(report) => log::error!("{report:?}"),
+                }
+            }
+        })
+    }
+}
@@ -1,2 +1,206 @@
+use std::fmt;
+use std::io;
+
+use actix_web::dev::Decompress;
+use actix_web::http;
+use actix_web::web;
+use actix_web::HttpRequest;
+use actix_web::HttpResponse;
+use futures_channel::mpsc;
+use futures_util::future;
+use futures_util::stream::IntoAsyncRead;
+use futures_util::SinkExt;
+use futures_util::Stream;
+use futures_util::StreamExt;
+use futures_util::TryStreamExt;
+
+use crate::db::DbTrait;
+use crate::errors::AppResponseError;
+use crate::models::load::LoadReport;
+use crate::models::load::ReadError;
+
+use api::params::DateTimeRange;
+use api::params::ImportMode;
+use api::params::LoadParams;
+use api::requests::logs::NewLog;
+use api::responses::load::LoadResponse;
+use api::validation::ValidationRules;
+
 pub mod csv;
+pub mod export;
+pub mod health;
+pub mod ingest;
 pub mod logs;
+pub mod stats;
+
+const BODY_CHANNEL_CHUNKS: usize = 16;
+
+type BodyReader = IntoAsyncRead<mpsc::Receiver<io::Result<web::Bytes>>>;
+
+/// size limits of request bodies, registered with `App::app_data` like `web::JsonConfig`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UploadLimits {
+    /// json bodies, which are read into memory as a whole
+    pub json_body: usize,
+    /// the json body of a single log
+    pub log_body: usize,
+    /// bodies that are streamed into the database, like csv files. no limit when `None`
+    pub upload: Option<u64>,
+}
+
+impl UploadLimits {
+    pub const DEFAULT_JSON_BODY: usize = 64 * 1024 * 1024;
+    /// like the default of `web::JsonConfig`
+    pub const DEFAULT_LOG_BODY: usize = 2 * 1024 * 1024;
+
+    fn from_req(req: &HttpRequest) -> Self {
+        req.app_data::<Self>().copied().unwrap_or_default()
+    }
+}
+
+impl Default for UploadLimits {
+    fn default() -> Self {
+        Self {
+            json_body: Self::DEFAULT_JSON_BODY,
+            log_body: Self::DEFAULT_LOG_BODY,
+            upload: None,
+        }
+    }
+}
+
+// rules for new logs, registered with `App::app_data` like `UploadLimits`
+fn validation_rules(req: &HttpRequest) -> ValidationRules {
+    req.app_data::<ValidationRules>()
+        .copied()
+        .unwrap_or_default()
+}
+
+// the limit of a streamed upload, checked against `Content-Length` before anything is read
+fn upload_limit(req: &HttpRequest) -> Result<Option<u64>, AppResponseError> {
+    let limit = UploadLimits::from_req(req).upload;
+    match (limit, content_length(req)) {
+        (Some(limit), Some(length)) if length > limit => Err(AppResponseError::PayloadTooLarge),
+        _ => Ok(limit),
+    }
+}
+
+// the request body, decoded by its `Content-Encoding` like the bodies of `web::Json`
+fn decoded_body(req: &HttpRequest, payload: web::Payload) -> Decompress<web::Payload> {
+    Decompress::from_headers(payload, req.headers())
+}
+
+// size of the whole upload, when the client tells it
+fn content_length(req: &HttpRequest) -> Option<u64> {
+    req.headers()
+        .get(http::header::CONTENT_LENGTH)
+        .and_then(|length| length.to_str().ok())
+        .and_then(|length| length.parse().ok())
+}
+
+// a range that ends before it starts is a mistake of the client, not an empty range
+fn checked_range(range: DateTimeRange) -> Result<DateTimeRange, AppResponseError> {
+    match range {
+        DateTimeRange {
+            from: Some(from),
+            until: Some(until),
+        } if from > until => Err(AppResponseError::Validation(format!(
+            "`from` must not be after `until` ({range})"
+        ))),
+        range => Ok(range),
+    }
+}
+
+// pass the bytes of a request body (or a multipart field) to the loader, and count them
+//
+// the body can't be sent to another thread, so its bytes are passed to the loader through
+// a channel, and both run in the handler
+//
+// a broken upload, or one over `limit`, is also passed as an io error, so that the loader
+// doesn't take it as the end of the data
+async fn forward_body<S, E>(
+    body: &mut S,
+    mut sender: mpsc::Sender<io::Result<web::Bytes>>,
+    limit: Option<u64>,
+) -> Result<u64, AppResponseError>
+where
+    S: Stream<Item = Result<web::Bytes, E>> + Unpin,
+    E: fmt::Display + Into<AppResponseError>,
+{
+    let mut received = 0;
+    while let Some(bytes) = body.next().await {
+        match bytes {
+            Ok(bytes) => {
+                received += bytes.len() as u64;
+                if limit.is_some_and(|limit| received > limit) {
+                    let error = io::Error::other("the upload is too large");
+                    let _ = sender.send(Err(error)).await;
+                    return Err(AppResponseError::PayloadTooLarge);
+                }
+                if sender.send(Ok(bytes)).await.is_err() {
+                    // the loader has stopped reading
+                    break;
+                }
+            }
+            Err(e) => {
+                let error = io::Error::other(e.to_string());
+                let _ = sender.send(Err(error)).await;
+                return Err(e.into());
+            }
+        }
+    }
+    Ok(received)
+}
+
+// load logs read from the request body by `read`
+async fn load_body<DB, B, E, F, S>(
+    app_state: &DB,
+    req: &HttpRequest,
+    payload: &mut B,
+    params: &LoadParams,
+    read: F,
+) -> Result<LoadReport, AppResponseError>
+where
+    DB: DbTrait,
+    B: Stream<Item = Result<web::Bytes, E>> + Unpin,
+    E: fmt::Display + Into<AppResponseError>,
+    F: FnOnce(BodyReader) -> S,
+    S: Stream<Item = Result<NewLog, ReadError>> + Send,
+{
+    let limit = upload_limit(req)?;
+    let size_hint = content_length(req);
+    let (sender, receiver) = mpsc::channel(BODY_CHANNEL_CHUNKS);
+    let load = async {
+        let new_logs = read(receiver.into_async_read());
+        let report = app_state.load_logs(new_logs, params, size_hint).await?;
+        Ok(report)
+    };
+    let (_, report) = future::try_join(forward_body(payload, sender, limit), load).await?;
+
+    Ok(report)
+}
+
+// the whole request body, for formats that can't be read as a stream
+async fn read_body<B, E>(payload: &mut B, limit: usize) -> Result<web::BytesMut, AppResponseError>
+where
+    B: Stream<Item = Result<web::Bytes, E>> + Unpin,
+    E: Into<AppResponseError>,
+{
+    let mut body = web::BytesMut::new();
+    while let Some(bytes) = payload.next().await {
+        let bytes = bytes.map_err(Into::into)?;
+        if body.len() + bytes.len() > limit {
+            return Err(AppResponseError::PayloadTooLarge);
+        }
+        body.extend_from_slice(&bytes);
+    }
+    Ok(body)
+}
+
+// in strict / atomic mode, an upload with a bad row is rejected
+fn load_response(report: LoadReport, mode: ImportMode) -> HttpResponse {
+    let response = LoadResponse::from(report);
+    if mode.stops_on_error() && response.rejected > 0 {
+        return HttpResponse::UnprocessableEntity().json(response);
+    }
+    HttpResponse::Ok().json(response)
+}
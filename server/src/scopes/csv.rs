@@ -1,22 +1,46 @@
-use std::io::Write;
-
+use actix_multipart::Field;
 use actix_multipart::Multipart;
 use actix_web::http;
 use actix_web::web;
+use actix_web::HttpRequest;
 use actix_web::HttpResponse;
 use actix_web::Responder;
+use async_compression::futures::bufread::BzDecoder;
+use async_compression::futures::bufread::GzipDecoder;
+use async_compression::futures::bufread::ZstdDecoder;
+use chrono::DateTime;
+use chrono::Utc;
 use error_stack::IntoReport;
 use error_stack::ResultExt;
+use futures_channel::mpsc;
+use futures_util::future;
+use futures_util::AsyncBufRead;
+use futures_util::AsyncRead;
 use futures_util::StreamExt;
+use futures_util::TryStreamExt;
 
+use crate::db::csv::read_new_logs;
 use crate::db::DbTrait;
 use crate::errors::AppError;
 use crate::errors::AppResponseError;
-
+use crate::models::load::LoadReport;
+use crate::models::logs::Log;
+use crate::scopes::checked_range;
+use crate::scopes::content_length;
+use crate::scopes::forward_body;
+use crate::scopes::load_response;
+use crate::scopes::upload_limit;
+use crate::scopes::validation_rules;
+
+use api::params::CsvParams;
 use api::params::DateTimeRange;
-use api::responses::csv::CsvResponse;
+use api::params::LoadParams;
+use api::params::LogFilter;
 use api::responses::logs::LogResponse;
 
+const CSV_CHUNK_ROWS: usize = 1000;
+const FIELD_CHANNEL_CHUNKS: usize = 16;
+
 pub fn csv_scope<DB: DbTrait + 'static>(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/csv")
@@ -27,66 +51,159 @@ pub fn csv_scope<DB: DbTrait + 'static>(cfg: &mut web::ServiceConfig) {
 
 async fn post_csv<DB: DbTrait>(
     app_state: web::Data<DB>,
+    req: HttpRequest,
+    params: web::Query<LoadParams>,
+    csv_params: web::Query<CsvParams>,
     mut multi_part: Multipart,
 ) -> Result<impl Responder, AppResponseError> {
-    let mut line_count = 0;
+    // a multipart body can't be looked ahead, so the size of the request is only taken for the
+    // first file. a file that tells its own size is taken by it
+    let mut size_hint = content_length(&req);
+    let limit = upload_limit(&req)?;
+    let mut received = 0;
+    let mode = params.mode;
+    let max_errors = params.max_errors();
+    let mut report = LoadReport::new(max_errors);
+    let rules = validation_rules(&req);
 
     while let Some(field) = multi_part.next().await {
         let mut field = field?;
 
-        if field
-            .content_type()
-            .is_some_and(|content_type| *content_type == mime::TEXT_CSV)
-        {
-            let mut tmpfile = tempfile::Builder::new()
-                .append(true)
-                .tempfile()
-                .into_report()
-                .change_context(AppError)?;
-
-            while let Some(bytes) = field.next().await {
-                let bytes = bytes?;
-
-                tmpfile
-                    .write_all(&bytes)
-                    .into_report()
-                    .change_context(AppError)?;
-            }
-            tmpfile.flush().into_report().change_context(AppError)?;
-            line_count += app_state.load_file(tmpfile.path()).await?;
+        // fields that are not csv are ignored
+        let Some(compression) = field_compression(&field) else {
+            continue;
+        };
+
+        let file_size = field_length(&field).or_else(|| size_hint.take());
+        let (sender, receiver) = mpsc::channel(FIELD_CHANNEL_CHUNKS);
+        let load = async {
+            let reader = decompress(receiver.into_async_read(), compression);
+            let new_logs = read_new_logs(reader, &csv_params.columns, rules);
+            let file_report = app_state.load_logs(new_logs, &params, file_size).await?;
+            Ok(file_report)
+        };
+        // the limit is for the whole request, the fields before this one are counted
+        let remaining = limit.map(|limit| limit.saturating_sub(received));
+        let forward = forward_body(&mut field, sender, remaining);
+        let (field_size, file_report) = future::try_join(forward, load).await?;
+        received += field_size;
+        report.merge(file_report);
+
+        // in strict / atomic mode, the upload is rejected at the first bad row.
+        // atomicity is per file, the files before this one are already committed
+        if mode.stops_on_error() && report.rejected > 0 {
+            return Ok(load_response(report, mode));
         }
     }
 
-    let response = CsvResponse(line_count);
-    Ok(HttpResponse::Ok().json(response))
+    Ok(load_response(report, mode))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Compression {
+    Identity,
+    Gzip,
+    Zstd,
+    Bzip2,
+}
+
+fn decompress<'r, R>(reader: R, compression: Compression) -> Box<dyn AsyncRead + Send + Unpin + 'r>
+where
+    R: AsyncBufRead + Send + Unpin + 'r,
+{
+    match compression {
+        Compression::Identity => Box::new(reader),
+        Compression::Gzip => {
+            let mut decoder = GzipDecoder::new(reader);
+            // rotated logs are often concatenated gzip files
+            decoder.multiple_members(true);
+            Box::new(decoder)
+        }
+        Compression::Zstd => Box::new(ZstdDecoder::new(reader)),
+        Compression::Bzip2 => Box::new(BzDecoder::new(reader)),
+    }
+}
+
+// size of a multipart field, when the client tells it
+fn field_length(field: &Field) -> Option<u64> {
+    field
+        .headers()
+        .get(http::header::CONTENT_LENGTH)
+        .and_then(|length| length.to_str().ok())
+        .and_then(|length| length.parse().ok())
+}
+
+// csv fields are `text/csv`, optionally with a `Content-Encoding` header,
+// or compressed files like `application/gzip`
+fn field_compression(field: &Field) -> Option<Compression> {
+    let content_type = field.content_type()?;
+
+    match (content_type.type_(), content_type.subtype().as_str()) {
+        (mime::TEXT, "csv") => {
+            let encoding = field
+                .headers()
+                .get(http::header::CONTENT_ENCODING)
+                .and_then(|encoding| encoding.to_str().ok());
+            match encoding.map(|encoding| encoding.trim().to_ascii_lowercase()) {
+                None => Some(Compression::Identity),
+                Some(encoding) => match encoding.as_str() {
+                    "identity" => Some(Compression::Identity),
+                    "gzip" | "x-gzip" => Some(Compression::Gzip),
+                    "zstd" => Some(Compression::Zstd),
+                    "bzip2" | "x-bzip2" => Some(Compression::Bzip2),
+                    _ => None,
+                },
+            }
+        }
+        (mime::APPLICATION, "gzip" | "x-gzip") => Some(Compression::Gzip),
+        (mime::APPLICATION, "zstd") => Some(Compression::Zstd),
+        (mime::APPLICATION, "x-bzip2") => Some(Compression::Bzip2),
+        _ => None,
+    }
 }
 
 async fn get_csv<DB: DbTrait>(
     app_state: web::Data<DB>,
     range: web::Query<DateTimeRange>,
+    filter: web::Query<LogFilter>,
 ) -> Result<impl Responder, AppResponseError> {
-    let DateTimeRange { from, until } = range.into_inner();
+    let DateTimeRange { from, until } = checked_range(range.into_inner())?;
 
-    let logs = app_state.get_logs(from, until).await?;
+    Ok(csv_logs_response(app_state.get_ref(), from, until, &filter))
+}
 
+// all logs in the range as csv, also sent by `GET /logs` for `Accept: text/csv`
+pub(crate) fn csv_logs_response<DB: DbTrait>(
+    app_state: &DB,
+    from: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+    filter: &LogFilter,
+) -> HttpResponse {
+    // rows that are already fetched are written out together as one chunk
+    let body = app_state
+        .stream_logs(from, until, filter)
+        .ready_chunks(CSV_CHUNK_ROWS)
+        .map(|logs| {
+            let logs = logs.into_iter().collect::<Result<Vec<_>, _>>()?;
+            let csv = logs_to_csv(logs)?;
+            Ok::<_, AppResponseError>(csv)
+        });
+
+    HttpResponse::Ok()
+        .insert_header((http::header::CONTENT_TYPE, mime::TEXT_CSV_UTF_8))
+        .streaming(body)
+}
+
+fn logs_to_csv(logs: Vec<Log>) -> error_stack::Result<web::Bytes, AppError> {
     let v = Vec::new();
     let mut w = csv::WriterBuilder::new().has_headers(false).from_writer(v);
 
     for log in logs {
-        let log_response = LogResponse {
-            user_agent: log.user_agent,
-            response_time: log.response_time,
-            timestamp: log.timestamp,
-        };
-        w.serialize(log_response)
+        w.serialize(LogResponse::from(log))
             .into_report()
             .change_context(AppError)?;
     }
 
     let csv = w.into_inner().into_report().change_context(AppError)?;
-    let response = HttpResponse::Ok()
-        .insert_header((http::header::CONTENT_TYPE, mime::TEXT_CSV_UTF_8))
-        .body(csv);
-
-    Ok(response)
+    Ok(web::Bytes::from(csv))
 }
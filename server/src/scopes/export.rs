@@ -0,0 +1,235 @@
+use std::io;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use actix_web::http;
+use actix_web::web;
+use actix_web::HttpResponse;
+use actix_web::Responder;
+use arrow_array::builder::FixedSizeBinaryBuilder;
+use arrow_array::ArrayRef;
+use arrow_array::Int32Array;
+use arrow_array::RecordBatch;
+use arrow_array::StringArray;
+use arrow_array::TimestampMicrosecondArray;
+use arrow_ipc::writer::FileWriter;
+use arrow_schema::DataType;
+use arrow_schema::Field;
+use arrow_schema::Schema;
+use arrow_schema::SchemaRef;
+use arrow_schema::TimeUnit;
+use error_stack::IntoReport;
+use error_stack::ResultExt;
+use futures_util::StreamExt;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::basic::ZstdLevel;
+use parquet::file::properties::WriterProperties;
+
+use crate::db::DbTrait;
+use crate::errors::AppError;
+use crate::errors::AppResponseError;
+use crate::models::logs::Log;
+use crate::scopes::checked_range;
+
+use api::params::DateTimeRange;
+use api::params::ExportFormat;
+use api::params::ExportParams;
+use api::params::LogFilter;
+
+/// rows in a parquet row group, and in an arrow record batch
+const ROW_GROUP_ROWS: usize = 64 * 1024;
+
+pub fn export_scope<DB: DbTrait + 'static>(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::scope("/export").route("", web::get().to(get_export::<DB>)));
+}
+
+// columnar files for analytics tools, written and sent one row group at a time
+async fn get_export<DB: DbTrait>(
+    app_state: web::Data<DB>,
+    range: web::Query<DateTimeRange>,
+    params: web::Query<ExportParams>,
+) -> Result<impl Responder, AppResponseError> {
+    let DateTimeRange { from, until } = checked_range(range.into_inner())?;
+    let format = params.format;
+
+    let mut writer = LogsWriter::new(format)?;
+    let mut logs = app_state
+        .stream_logs(from, until, &LogFilter::default())
+        .chunks(ROW_GROUP_ROWS);
+
+    let body = async_stream::stream! {
+        while let Some(logs) = logs.next().await {
+            let bytes = logs
+                .into_iter()
+                .collect::<Result<Vec<_>, _>>()
+                .and_then(|logs| writer.write(&logs));
+            match bytes {
+                Ok(bytes) => yield Ok(bytes),
+                Err(e) => {
+                    yield Err(AppResponseError::from(e));
+                    return;
+                }
+            }
+        }
+        yield writer.finish().map_err(AppResponseError::from);
+    };
+
+    let (content_type, extension) = match format {
+        ExportFormat::Parquet => ("application/vnd.apache.parquet", "parquet"),
+        ExportFormat::Arrow => ("application/vnd.apache.arrow.file", "arrow"),
+    };
+    let response = HttpResponse::Ok()
+        .insert_header((http::header::CONTENT_TYPE, content_type))
+        .insert_header((
+            http::header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"logs.{extension}\""),
+        ))
+        .streaming(body);
+
+    Ok(response)
+}
+
+/// schema of exported logs, `id` is a uuid as 16 bytes
+fn logs_schema() -> SchemaRef {
+    let id = Field::new("id", DataType::FixedSizeBinary(16), false)
+        .with_metadata([("ARROW:extension:name".to_string(), "arrow.uuid".to_string())].into());
+
+    Arc::new(Schema::new(vec![
+        id,
+        Field::new("user_agent", DataType::Utf8, false),
+        Field::new("response_time", DataType::Int32, false),
+        Field::new(
+            "timestamp",
+            DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())),
+            false,
+        ),
+    ]))
+}
+
+fn logs_to_batch(schema: &SchemaRef, logs: &[Log]) -> error_stack::Result<RecordBatch, AppError> {
+    let mut ids = FixedSizeBinaryBuilder::with_capacity(logs.len(), 16);
+    for log in logs {
+        ids.append_value(log.id.as_bytes())
+            .into_report()
+            .change_context(AppError)?;
+    }
+    let user_agents = StringArray::from_iter_values(logs.iter().map(|log| &log.user_agent));
+    let response_times = Int32Array::from_iter_values(logs.iter().map(|log| log.response_time));
+    let timestamps = TimestampMicrosecondArray::from_iter_values(
+        logs.iter().map(|log| log.timestamp.timestamp_micros()),
+    )
+    .with_timezone("UTC");
+
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(ids.finish()),
+        Arc::new(user_agents),
+        Arc::new(response_times),
+        Arc::new(timestamps),
+    ];
+    RecordBatch::try_new(schema.clone(), columns)
+        .into_report()
+        .change_context(AppError)
+}
+
+// the writers own their output, so they write into a buffer shared with `LogsWriter`,
+// which is drained after each row group
+#[derive(Debug, Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl SharedBuffer {
+    fn take(&self) -> web::Bytes {
+        web::Bytes::from(std::mem::take(&mut *self.0.lock().unwrap()))
+    }
+}
+
+impl io::Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+struct LogsWriter {
+    schema: SchemaRef,
+    buffer: SharedBuffer,
+    inner: Inner,
+}
+
+enum Inner {
+    Parquet(ArrowWriter<SharedBuffer>),
+    Arrow(FileWriter<SharedBuffer>),
+}
+
+impl LogsWriter {
+    fn new(format: ExportFormat) -> error_stack::Result<Self, AppError> {
+        let schema = logs_schema();
+        let buffer = SharedBuffer::default();
+
+        let inner = match format {
+            ExportFormat::Parquet => {
+                let properties = WriterProperties::builder()
+                    .set_compression(Compression::ZSTD(ZstdLevel::default()))
+                    .set_max_row_group_size(ROW_GROUP_ROWS)
+                    .build();
+                let writer = ArrowWriter::try_new(buffer.clone(), schema.clone(), Some(properties))
+                    .into_report()
+                    .change_context(AppError)?;
+                Inner::Parquet(writer)
+            }
+            ExportFormat::Arrow => {
+                let writer = FileWriter::try_new(buffer.clone(), &schema)
+                    .into_report()
+                    .change_context(AppError)?;
+                Inner::Arrow(writer)
+            }
+        };
+
+        Ok(Self {
+            schema,
+            buffer,
+            inner,
+        })
+    }
+
+    /// write the logs as one row group, and return the bytes written so far
+    fn write(&mut self, logs: &[Log]) -> error_stack::Result<web::Bytes, AppError> {
+        let batch = logs_to_batch(&self.schema, logs)?;
+
+        match &mut self.inner {
+            Inner::Parquet(writer) => {
+                writer
+                    .write(&batch)
+                    .into_report()
+                    .change_context(AppError)?;
+                writer.flush().into_report().change_context(AppError)?;
+            }
+            Inner::Arrow(writer) => {
+                writer
+                    .write(&batch)
+                    .into_report()
+                    .change_context(AppError)?;
+            }
+        }
+
+        Ok(self.buffer.take())
+    }
+
+    /// write the footer, and return the rest of the file
+    fn finish(self) -> error_stack::Result<web::Bytes, AppError> {
+        match self.inner {
+            Inner::Parquet(writer) => {
+                writer.close().into_report().change_context(AppError)?;
+            }
+            Inner::Arrow(mut writer) => {
+                writer.finish().into_report().change_context(AppError)?;
+            }
+        }
+
+        Ok(self.buffer.take())
+    }
+}
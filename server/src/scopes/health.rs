@@ -0,0 +1,76 @@
+use std::future::Future;
+use std::time::Duration;
+
+use actix_web::http::StatusCode;
+use actix_web::rt::time;
+use actix_web::web;
+use actix_web::HttpResponse;
+use actix_web::Responder;
+
+use crate::db::DbTrait;
+use crate::errors::AppError;
+
+use api::responses::health::HealthResponse;
+use api::responses::health::ReadinessResponse;
+use api::responses::health::VersionResponse;
+
+// probes are expected to answer quickly, even when the database doesn't
+const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// probes for orchestrators, at the root like other servers have them
+pub fn health_scope<DB: DbTrait + 'static>(cfg: &mut web::ServiceConfig) {
+    cfg.route("/healthz", web::get().to(get_healthz))
+        .route("/readyz", web::get().to(get_readyz::<DB>))
+        .route("/version", web::get().to(get_version::<DB>));
+}
+
+// the process is alive, the database is left to the readiness probe
+async fn get_healthz() -> impl Responder {
+    HttpResponse::Ok().json(HealthResponse {
+        status: "ok".into(),
+    })
+}
+
+async fn get_readyz<DB: DbTrait>(app_state: web::Data<DB>) -> impl Responder {
+    let pool = app_state.pool_status();
+
+    // the ping would only wait for a connection from a saturated pool
+    let database = !pool.is_saturated() && probe(app_state.ping()).await.is_some();
+
+    let status = if database {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    HttpResponse::build(status).json(ReadinessResponse {
+        ready: database,
+        database,
+        pool: pool.into(),
+    })
+}
+
+async fn get_version<DB: DbTrait>(app_state: web::Data<DB>) -> impl Responder {
+    // the version of the server is still useful while the database is down
+    let migration_version = probe(app_state.migration_version()).await.flatten();
+
+    HttpResponse::Ok().json(VersionResponse {
+        version: env!("CARGO_PKG_VERSION").into(),
+        git_sha: option_env!("GIT_SHA").map(String::from),
+        migration_version,
+    })
+}
+
+// the answer of the database, or `None` when it failed or took longer than `PROBE_TIMEOUT`
+async fn probe<T>(query: impl Future<Output = error_stack::Result<T, AppError>>) -> Option<T> {
+    match time::timeout(PROBE_TIMEOUT, query).await {
+        Ok(Ok(answer)) => Some(answer),
+        Ok(Err(report)) => {
+            log::warn!("{report:?}");
+            None
+        }
+        Err(_) => {
+            log::warn!("The database didn't answer in {PROBE_TIMEOUT:?}");
+            None
+        }
+    }
+}
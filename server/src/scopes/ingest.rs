@@ -0,0 +1,38 @@
+use actix_web::web;
+use actix_web::HttpRequest;
+use actix_web::Responder;
+
+use crate::db::access_log::read_access_logs;
+use crate::db::DbTrait;
+use crate::errors::AppResponseError;
+use crate::scopes::decoded_body;
+use crate::scopes::load_body;
+use crate::scopes::load_response;
+use crate::scopes::validation_rules;
+
+use api::params::AccessLogParams;
+use api::params::LoadParams;
+
+pub fn ingest_scope<DB: DbTrait + 'static>(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::scope("/ingest").route("/access-log", web::post().to(post_access_log::<DB>)));
+}
+
+// web server access log lines, loaded in batches like csv uploads
+async fn post_access_log<DB: DbTrait>(
+    app_state: web::Data<DB>,
+    req: HttpRequest,
+    params: web::Query<LoadParams>,
+    access_log: web::Query<AccessLogParams>,
+    payload: web::Payload,
+) -> Result<impl Responder, AppResponseError> {
+    let format = access_log.format;
+    let rules = validation_rules(&req);
+    let mut payload = decoded_body(&req, payload);
+
+    let report = load_body(app_state.get_ref(), &req, &mut payload, &params, |reader| {
+        read_access_logs(reader, format, rules)
+    })
+    .await?;
+
+    Ok(load_response(report, params.mode))
+}
@@ -1,50 +1,421 @@
+use std::fmt;
+use std::io;
+
+use actix_web::body;
+use actix_web::body::BoxBody;
+use actix_web::error::PayloadError;
+use actix_web::http;
+use actix_web::http::header::Header;
+use actix_web::http::StatusCode;
 use actix_web::web;
+use actix_web::HttpMessage;
+use actix_web::HttpRequest;
 use actix_web::HttpResponse;
 use actix_web::Responder;
+use error_stack::IntoReport;
+use error_stack::ResultExt;
+use futures_util::stream;
+use futures_util::Stream;
+use futures_util::StreamExt;
+use sha2::Digest;
+use sha2::Sha256;
+use uuid::Uuid;
 
+use crate::db::csv::read_new_logs;
+use crate::db::json::read_json_logs;
+use crate::db::json::read_ndjson_logs;
 use crate::db::DbTrait;
+use crate::errors::AppError;
 use crate::errors::AppResponseError;
+use crate::models::idempotency::IdempotencyClaim;
+use crate::models::idempotency::StoredResponse;
+use crate::models::logs::InsertedLog;
+use crate::models::logs::Log;
+use crate::scopes::checked_range;
+use crate::scopes::csv::csv_logs_response;
+use crate::scopes::decoded_body;
+use crate::scopes::load_body;
+use crate::scopes::load_response;
+use crate::scopes::read_body;
+use crate::scopes::validation_rules;
+use crate::scopes::UploadLimits;
 
+use api::params::CsvParams;
 use api::params::DateTimeRange;
+use api::params::LoadParams;
+use api::params::LogFilter;
+use api::params::Pagination;
 use api::requests::logs::NewLog;
+use api::responses::logs::DeleteResponse;
 use api::responses::logs::LogResponse;
+use api::responses::logs::LogsResponse;
+
+const NDJSON_CHUNK_LOGS: usize = 1000;
+const NDJSON: &str = "application/x-ndjson";
+const IDEMPOTENCY_KEY: &str = "Idempotency-Key";
+const IDEMPOTENCY_KEY_MAX_LEN: usize = 255;
+const IDEMPOTENT_REPLAYED: &str = "Idempotent-Replayed";
 
 pub fn logs_scope<DB: DbTrait + 'static>(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/logs")
             .route("", web::post().to(post_logs::<DB>))
-            .route("", web::get().to(get_logs::<DB>)),
+            .route("", web::get().to(get_logs::<DB>))
+            .route("", web::delete().to(delete_logs::<DB>))
+            .route("/bulk", web::post().to(post_bulk_logs::<DB>))
+            .route("/{id}", web::get().to(get_log::<DB>))
+            .route("/{id}", web::delete().to(delete_log::<DB>)),
     );
 }
 
+// with an `Idempotency-Key`, the response is stored and replayed when the request is sent again.
+// a request that failed with an error can be sent again with the same key, the key can't be used
+// for another request.
+// the body is read into memory to be hashed, so it is limited like json bodies
 async fn post_logs<DB: DbTrait>(
     app_state: web::Data<DB>,
-    new_log: web::Json<NewLog>,
+    req: HttpRequest,
+    params: web::Query<LoadParams>,
+    csv_params: web::Query<CsvParams>,
+    payload: web::Payload,
+) -> Result<HttpResponse, AppResponseError> {
+    let app_state = app_state.get_ref();
+    let mut payload = decoded_body(&req, payload);
+    let Some(key) = idempotency_key(&req)? else {
+        return create_logs(app_state, &req, &params, &csv_params, &mut payload).await;
+    };
+
+    let limit = UploadLimits::from_req(&req).json_body;
+    let body = read_body(&mut payload, limit).await?.freeze();
+    let request_hash = request_hash(&req, &body);
+    match app_state.claim_idempotency_key(&key, &request_hash).await? {
+        IdempotencyClaim::Claimed => {}
+        IdempotencyClaim::InProgress => {
+            return Err(AppResponseError::Conflict(format!(
+                "a request with the idempotency key `{key}` is in progress"
+            )));
+        }
+        IdempotencyClaim::Completed(stored) => {
+            let status = StatusCode::from_u16(stored.status)
+                .into_report()
+                .change_context(AppError)?;
+            let mut response = HttpResponse::build(status);
+            response.insert_header((IDEMPOTENT_REPLAYED, "true"));
+            if let Some(content_type) = stored.content_type {
+                response.content_type(content_type);
+            }
+            return Ok(response.body(stored.body));
+        }
+        IdempotencyClaim::Mismatch => return Err(AppResponseError::IdempotencyKeyReused(key)),
+    }
+
+    let mut body = stream::iter([Ok::<_, PayloadError>(body)]);
+    let response = match create_logs(app_state, &req, &params, &csv_params, &mut body).await {
+        Ok(response) => response,
+        Err(e) => {
+            app_state.release_idempotency_key(&key).await?;
+            return Err(e);
+        }
+    };
+
+    let (response, body) = response.into_parts();
+    let body = body::to_bytes(body)
+        .await
+        .map_err(|e| io::Error::other(e.to_string()))
+        .into_report()
+        .change_context(AppError)?;
+    let stored = StoredResponse {
+        status: response.status().as_u16(),
+        content_type: response
+            .headers()
+            .get(http::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string),
+        body: body.to_vec(),
+    };
+    app_state.complete_idempotency_key(&key, &stored).await?;
+
+    Ok(response.set_body(BoxBody::new(body)))
+}
+
+// one `NewLog` as json, or many logs as csv or ndjson like `/csv` and `/logs/bulk`
+async fn create_logs<DB, B, E>(
+    app_state: &DB,
+    req: &HttpRequest,
+    params: &LoadParams,
+    csv_params: &CsvParams,
+    payload: &mut B,
+) -> Result<HttpResponse, AppResponseError>
+where
+    DB: DbTrait,
+    B: Stream<Item = Result<web::Bytes, E>> + Unpin,
+    E: fmt::Display + Into<AppResponseError>,
+{
+    let rules = validation_rules(req);
+
+    let report = match content_type(req)? {
+        LogsMediaType::Json => {
+            let body = read_body(payload, UploadLimits::from_req(req).log_body).await?;
+            let NewLog {
+                user_agent,
+                response_time,
+                timestamp,
+                id,
+            } = rules.validate(serde_json::from_slice(&body)?)?;
+
+            let stored_log = match app_state
+                .insert_log(&user_agent, response_time, timestamp, id)
+                .await?
+            {
+                InsertedLog::New(new_log) => {
+                    return Ok(HttpResponse::Created().json(LogResponse::from(new_log)));
+                }
+                InsertedLog::Existing(stored_log) => stored_log,
+            };
+
+            // sending a log again is fine, another log with its id is not
+            let same = stored_log.user_agent == user_agent
+                && stored_log.response_time == response_time
+                && timestamp.is_none_or(|timestamp| stored_log.timestamp == timestamp);
+            if !same {
+                return Err(AppResponseError::Conflict(format!(
+                    "another log with the id `{}` is stored",
+                    stored_log.id
+                )));
+            }
+            return Ok(HttpResponse::Ok().json(LogResponse::from(stored_log)));
+        }
+        LogsMediaType::Csv => {
+            let read = |reader| read_new_logs(reader, &csv_params.columns, rules);
+            load_body(app_state, req, payload, params, read).await?
+        }
+        LogsMediaType::Ndjson => {
+            let read = |reader| read_ndjson_logs(reader, rules);
+            load_body(app_state, req, payload, params, read).await?
+        }
+    };
+
+    Ok(load_response(report, params.mode))
+}
+
+// many logs in one request, as ndjson or a json array
+//
+// they are loaded in batches like csv uploads, and the response tells which lines were rejected
+async fn post_bulk_logs<DB: DbTrait>(
+    app_state: web::Data<DB>,
+    req: HttpRequest,
+    params: web::Query<LoadParams>,
+    payload: web::Payload,
 ) -> Result<impl Responder, AppResponseError> {
-    let NewLog {
-        user_agent,
-        response_time,
-        timestamp,
-    } = new_log.into_inner();
-
-    let new_log = app_state
-        .insert_log(&user_agent, response_time, timestamp)
+    let rules = validation_rules(&req);
+    let mut payload = decoded_body(&req, payload);
+    let content_type = req.mime_type().ok().flatten();
+    let report = match content_type
+        .as_ref()
+        .map(|mime| (mime.type_(), mime.subtype().as_str()))
+    {
+        Some((mime::APPLICATION, "x-ndjson" | "ndjson")) => {
+            load_body(app_state.get_ref(), &req, &mut payload, &params, |reader| {
+                read_ndjson_logs(reader, rules)
+            })
+            .await?
+        }
+        Some((mime::APPLICATION, "json")) => {
+            let body = read_body(&mut payload, UploadLimits::from_req(&req).json_body).await?;
+            let new_logs = read_json_logs(&body, rules)?;
+            app_state
+                .load_logs(stream::iter(new_logs), &params, Some(body.len() as u64))
+                .await?
+        }
+        _ => {
+            let content_type = content_type.map(|mime| mime.to_string());
+            return Err(AppResponseError::UnsupportedMediaType(
+                content_type.unwrap_or_default(),
+            ));
+        }
+    };
+
+    Ok(load_response(report, params.mode))
+}
+
+// the json response is paged, csv and ndjson stream all logs in the range
+async fn get_logs<DB: DbTrait>(
+    app_state: web::Data<DB>,
+    req: HttpRequest,
+    range: web::Query<DateTimeRange>,
+    filter: web::Query<LogFilter>,
+    pagination: web::Query<Pagination>,
+) -> Result<HttpResponse, AppResponseError> {
+    let DateTimeRange { from, until } = checked_range(range.into_inner())?;
+
+    match accepted_type(&req)? {
+        LogsMediaType::Json => {}
+        LogsMediaType::Csv => {
+            return Ok(csv_logs_response(app_state.get_ref(), from, until, &filter));
+        }
+        LogsMediaType::Ndjson => {
+            let body = app_state
+                .stream_logs(from, until, &filter)
+                .ready_chunks(NDJSON_CHUNK_LOGS)
+                .map(|logs| {
+                    let logs = logs.into_iter().collect::<Result<Vec<_>, _>>()?;
+                    let ndjson = logs_to_ndjson(logs)?;
+                    Ok::<_, AppResponseError>(ndjson)
+                });
+
+            return Ok(HttpResponse::Ok()
+                .insert_header((http::header::CONTENT_TYPE, NDJSON))
+                .streaming(body));
+        }
+    }
+
+    let limit = pagination.limit() as usize;
+
+    // fetch one extra log to find out whether there is a next page
+    let mut logs = app_state
+        .get_logs(from, until, &filter, pagination.cursor, limit as i64 + 1)
         .await?;
 
-    let response = HttpResponse::Created().json(LogResponse::from(new_log));
+    let next_cursor = if logs.len() > limit {
+        logs.truncate(limit);
+        logs.last().map(Log::cursor)
+    } else {
+        None
+    };
+
+    let response = LogsResponse {
+        logs: logs.into_iter().map(LogResponse::from).collect(),
+        next_cursor,
+    };
 
-    Ok(response)
+    Ok(HttpResponse::Ok().json(response))
 }
 
-async fn get_logs<DB: DbTrait>(
+async fn get_log<DB: DbTrait>(
+    app_state: web::Data<DB>,
+    id: web::Path<Uuid>,
+) -> Result<impl Responder, AppResponseError> {
+    let log = app_state
+        .get_log(id.into_inner())
+        .await?
+        .ok_or(AppResponseError::NotFound)?;
+
+    Ok(HttpResponse::Ok().json(LogResponse::from(log)))
+}
+
+async fn delete_log<DB: DbTrait>(
+    app_state: web::Data<DB>,
+    id: web::Path<Uuid>,
+) -> Result<impl Responder, AppResponseError> {
+    if !app_state.delete_log(id.into_inner()).await? {
+        return Err(AppResponseError::NotFound);
+    }
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+// a range without bounds is refused, so a bare `DELETE /logs` can't empty the table
+async fn delete_logs<DB: DbTrait>(
     app_state: web::Data<DB>,
     range: web::Query<DateTimeRange>,
 ) -> Result<impl Responder, AppResponseError> {
-    let DateTimeRange { from, until } = range.into_inner();
+    let DateTimeRange { from, until } = checked_range(range.into_inner())?;
+    if from.is_none() && until.is_none() {
+        return Err(AppResponseError::Validation(
+            "`from` or `until` is required".to_string(),
+        ));
+    }
+
+    let deleted = app_state.delete_logs(from, until).await?;
+
+    Ok(HttpResponse::Ok().json(DeleteResponse { deleted }))
+}
 
-    let logs = app_state.get_logs(from, until).await?;
+fn logs_to_ndjson(logs: Vec<Log>) -> Result<web::Bytes, AppResponseError> {
+    let mut ndjson = Vec::new();
+    for log in logs {
+        serde_json::to_writer(&mut ndjson, &LogResponse::from(log))?;
+        ndjson.push(b'\n');
+    }
+    Ok(web::Bytes::from(ndjson))
+}
+
+/// representations of the `/logs` resource
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LogsMediaType {
+    Json,
+    Csv,
+    Ndjson,
+}
+
+impl LogsMediaType {
+    fn from_mime(mime: &mime::Mime) -> Option<Self> {
+        match (mime.type_(), mime.subtype().as_str()) {
+            (mime::APPLICATION, "json") => Some(Self::Json),
+            (mime::TEXT, "csv") => Some(Self::Csv),
+            (mime::APPLICATION, "x-ndjson" | "ndjson") => Some(Self::Ndjson),
+            _ => None,
+        }
+    }
+}
+
+// json when the client accepts anything, or doesn't tell
+fn accepted_type(req: &HttpRequest) -> Result<LogsMediaType, AppResponseError> {
+    let accept = http::header::Accept::parse(req).unwrap_or_else(|_| http::header::Accept(vec![]));
+    if accept.is_empty() {
+        return Ok(LogsMediaType::Json);
+    }
+
+    accept
+        .ranked()
+        .iter()
+        .find_map(|mime| match (mime.type_(), mime.subtype()) {
+            (mime::STAR, mime::STAR) | (mime::APPLICATION, mime::STAR) => Some(LogsMediaType::Json),
+            (mime::TEXT, mime::STAR) => Some(LogsMediaType::Csv),
+            _ => LogsMediaType::from_mime(mime),
+        })
+        .ok_or_else(|| AppResponseError::NotAcceptable(accept.to_string()))
+}
+
+// printable ascii, like the keys of other APIs (uuids, or random strings)
+fn idempotency_key(req: &HttpRequest) -> Result<Option<String>, AppResponseError> {
+    let Some(key) = req.headers().get(IDEMPOTENCY_KEY) else {
+        return Ok(None);
+    };
+
+    key.to_str()
+        .ok()
+        .filter(|key| (1..=IDEMPOTENCY_KEY_MAX_LEN).contains(&key.len()))
+        .filter(|key| key.bytes().all(|b| b.is_ascii_graphic()))
+        .map(|key| Some(key.to_string()))
+        .ok_or_else(|| {
+            AppResponseError::Validation(format!(
+                "{IDEMPOTENCY_KEY} must be 1 to {IDEMPOTENCY_KEY_MAX_LEN} printable ascii characters"
+            ))
+        })
+}
+
+// the method, path, query and body of a request, to tell it from another one with the same key
+fn request_hash(req: &HttpRequest, body: &[u8]) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    hasher.update(req.method().as_str());
+    hasher.update(b" ");
+    hasher.update(req.path());
+    hasher.update(b"?");
+    hasher.update(req.query_string());
+    hasher.update(b"\n");
+    hasher.update(body);
+    hasher.finalize().to_vec()
+}
 
-    let logs = logs.into_iter().map(LogResponse::from).collect::<Vec<_>>();
+fn content_type(req: &HttpRequest) -> Result<LogsMediaType, AppResponseError> {
+    let content_type = req.mime_type().ok().flatten();
 
-    Ok(HttpResponse::Ok().json(logs))
+    content_type
+        .as_ref()
+        .and_then(LogsMediaType::from_mime)
+        .ok_or_else(|| {
+            let content_type = content_type.map(|mime| mime.to_string());
+            AppResponseError::UnsupportedMediaType(content_type.unwrap_or_default())
+        })
 }
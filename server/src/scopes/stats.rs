@@ -0,0 +1,55 @@
+use actix_web::web;
+use actix_web::HttpResponse;
+use actix_web::Responder;
+
+use crate::db::DbTrait;
+use crate::errors::AppResponseError;
+use crate::scopes::checked_range;
+
+use api::params::DateTimeRange;
+use api::params::StatsParams;
+use api::params::TimeseriesParams;
+use api::responses::stats::BucketStatsResponse;
+use api::responses::stats::StatsResponse;
+
+pub fn stats_scope<DB: DbTrait + 'static>(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/stats")
+            .route("", web::get().to(get_stats::<DB>))
+            .route("/timeseries", web::get().to(get_timeseries::<DB>)),
+    );
+}
+
+async fn get_stats<DB: DbTrait>(
+    app_state: web::Data<DB>,
+    range: web::Query<DateTimeRange>,
+    params: web::Query<StatsParams>,
+) -> Result<impl Responder, AppResponseError> {
+    let DateTimeRange { from, until } = checked_range(range.into_inner())?;
+
+    let stats = app_state.get_stats(from, until, params.group_by).await?;
+
+    let stats = stats
+        .into_iter()
+        .map(StatsResponse::from)
+        .collect::<Vec<_>>();
+
+    Ok(HttpResponse::Ok().json(stats))
+}
+
+async fn get_timeseries<DB: DbTrait>(
+    app_state: web::Data<DB>,
+    range: web::Query<DateTimeRange>,
+    params: web::Query<TimeseriesParams>,
+) -> Result<impl Responder, AppResponseError> {
+    let DateTimeRange { from, until } = checked_range(range.into_inner())?;
+
+    let stats = app_state.get_timeseries(from, until, params.bucket).await?;
+
+    let stats = stats
+        .into_iter()
+        .map(BucketStatsResponse::from)
+        .collect::<Vec<_>>();
+
+    Ok(HttpResponse::Ok().json(stats))
+}
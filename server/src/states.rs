@@ -1,18 +1,87 @@
 use error_stack::IntoReport;
 use error_stack::ResultExt;
+use sqlx::postgres::PgPoolOptions;
 use sqlx::PgPool;
 
 use crate::errors::AppError;
 
-#[derive(Debug, Clone, derive_more::Deref, derive_more::From)]
-pub struct DbState(PgPool);
+#[derive(Debug, Clone, derive_more::Deref)]
+pub struct DbState {
+    #[deref]
+    pool: PgPool,
+    max_connections: u32,
+    batch_size: usize,
+    copy_threshold: u64,
+}
 
 impl DbState {
+    /// size of the pool, like the default of sqlx
+    pub const DEFAULT_MAX_CONNECTIONS: u32 = 10;
+    /// number of rows inserted at once when loading files
+    pub const DEFAULT_BATCH_SIZE: usize = 1000;
+    /// files of this size or larger are loaded by COPY, unless the upload chooses otherwise
+    pub const DEFAULT_COPY_THRESHOLD: u64 = 64 * 1024 * 1024;
+
     pub async fn new(database_url: &str) -> error_stack::Result<Self, AppError> {
-        let pool = PgPool::connect(database_url)
+        Self::connect(
+            database_url,
+            Self::DEFAULT_MAX_CONNECTIONS,
+            PgPoolOptions::new(),
+        )
+        .await
+    }
+
+    /// connect with a pool of at most `max_connections`, and the timeouts of `options`
+    ///
+    /// the size is kept to tell when the pool is saturated, sqlx doesn't give it back
+    pub async fn connect(
+        database_url: &str,
+        max_connections: u32,
+        options: PgPoolOptions,
+    ) -> error_stack::Result<Self, AppError> {
+        let pool = options
+            .max_connections(max_connections)
+            .connect(database_url)
             .await
             .into_report()
             .change_context(AppError)?;
-        Ok(Self(pool))
+        Ok(Self {
+            max_connections,
+            ..Self::from(pool)
+        })
+    }
+
+    pub fn with_batch_size(self, batch_size: usize) -> Self {
+        Self { batch_size, ..self }
+    }
+
+    pub fn with_copy_threshold(self, copy_threshold: u64) -> Self {
+        Self {
+            copy_threshold,
+            ..self
+        }
+    }
+
+    pub fn max_connections(&self) -> u32 {
+        self.max_connections
+    }
+
+    pub fn batch_size(&self) -> usize {
+        self.batch_size
+    }
+
+    pub fn copy_threshold(&self) -> u64 {
+        self.copy_threshold
+    }
+}
+
+impl From<PgPool> for DbState {
+    fn from(pool: PgPool) -> Self {
+        Self {
+            pool,
+            max_connections: Self::DEFAULT_MAX_CONNECTIONS,
+            batch_size: Self::DEFAULT_BATCH_SIZE,
+            copy_threshold: Self::DEFAULT_COPY_THRESHOLD,
+        }
     }
 }
@@ -0,0 +1,66 @@
+use chrono::DateTime;
+use chrono::Duration;
+use chrono::Utc;
+use futures_util::stream;
+use pretty_assertions::assert_eq;
+
+use server::db::DbTrait;
+use server::states::DbState;
+
+use api::params::ImportMode;
+use api::params::IngestMethod;
+use api::params::LoadParams;
+use api::requests::logs::NewLog;
+
+mod pg_db;
+
+async fn count_logs(db_state: &DbState, from: DateTime<Utc>) -> i64 {
+    sqlx::query_scalar("SELECT COUNT(*) FROM logs WHERE timestamp >= $1 AND timestamp < $2")
+        .bind(from)
+        .bind(from + Duration::days(1))
+        .fetch_one(&**db_state)
+        .await
+        .unwrap()
+}
+
+// the rows after the last full batch are written too
+#[actix_web::test]
+async fn load_more_rows_than_a_batch() {
+    let db_state = pg_db::connect(1).await.with_batch_size(3);
+    let start: DateTime<Utc> = "1986-01-01T00:00:00Z".parse().unwrap();
+
+    for method in [IngestMethod::Unnest, IngestMethod::Copy] {
+        for mode in [ImportMode::Lenient, ImportMode::Atomic] {
+            let new_logs = (0..10).map(|i| {
+                Ok(NewLog {
+                    user_agent: format!("agent {i}"),
+                    response_time: i,
+                    timestamp: Some(start + Duration::minutes(i.into())),
+                    id: None,
+                })
+            });
+            let params = LoadParams {
+                mode,
+                ingest: Some(method),
+                ..Default::default()
+            };
+            let report = db_state
+                .load_logs(stream::iter(new_logs), &params, None)
+                .await
+                .unwrap();
+
+            assert_eq!(report.accepted, 10, "{method:?} {mode:?}");
+            assert_eq!(
+                count_logs(&db_state, start).await,
+                10,
+                "{method:?} {mode:?}"
+            );
+
+            let deleted = db_state
+                .delete_logs(Some(start), Some(start + Duration::days(1)))
+                .await
+                .unwrap();
+            assert_eq!(deleted, 10, "{method:?} {mode:?}");
+        }
+    }
+}
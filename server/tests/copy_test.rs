@@ -0,0 +1,104 @@
+use futures_util::stream;
+use pretty_assertions::assert_eq;
+use uuid::Uuid;
+
+use server::db::DbTrait;
+
+use api::params::IngestMethod;
+use api::params::LoadParams;
+use api::requests::logs::NewLog;
+
+mod pg_db;
+
+// characters that COPY treats specially in its text and csv formats
+#[actix_web::test]
+async fn copy_special_characters() {
+    let db_state = pg_db::connect(1).await;
+
+    let user_agents = [
+        "agent\twith\ttabs",
+        "agent\\with\\backslashes\\N",
+        "agent\nwith\r\nnewlines",
+        "agent \"with\" quotes, and commas",
+        "NULL",
+        "\\N",
+        "\\.",
+        "エージェント",
+    ];
+    let timestamp = "1985-01-02T03:04:05Z".parse().unwrap();
+    let new_logs = user_agents
+        .iter()
+        .enumerate()
+        .map(|(i, user_agent)| NewLog {
+            user_agent: user_agent.to_string(),
+            response_time: i as i32,
+            timestamp: Some(timestamp),
+            id: Some(Uuid::new_v4()),
+        })
+        .collect::<Vec<_>>();
+
+    for method in [IngestMethod::Copy, IngestMethod::Unnest] {
+        let params = LoadParams {
+            ingest: Some(method),
+            ..Default::default()
+        };
+        let rows = new_logs.iter().cloned().map(Ok).collect::<Vec<_>>();
+        let report = db_state
+            .load_logs(stream::iter(rows), &params, None)
+            .await
+            .unwrap();
+        assert_eq!(report.accepted, new_logs.len() as u64, "{method:?}");
+
+        for new_log in &new_logs {
+            let id = new_log.id.unwrap();
+            let log = db_state.get_log(id).await.unwrap().unwrap();
+
+            assert_eq!(
+                (log.user_agent.as_str(), log.response_time, log.timestamp),
+                (
+                    new_log.user_agent.as_str(),
+                    new_log.response_time,
+                    timestamp
+                ),
+                "{method:?}"
+            );
+            assert!(db_state.delete_log(id).await.unwrap());
+        }
+    }
+}
+
+// the connection of a failed COPY can be used again
+#[actix_web::test]
+async fn copy_after_error() {
+    // one connection, so that both loads use it
+    let db_state = pg_db::connect(1).await;
+
+    let id = Uuid::new_v4();
+    let new_log = NewLog {
+        user_agent: "agent".into(),
+        response_time: 100,
+        timestamp: Some("1985-01-02T03:04:05Z".parse().unwrap()),
+        id: Some(id),
+    };
+    let params = LoadParams {
+        ingest: Some(IngestMethod::Copy),
+        ..Default::default()
+    };
+    // a user agent that is too long for the column fails the COPY
+    let too_long = NewLog {
+        user_agent: "a".repeat(1000),
+        id: Some(Uuid::new_v4()),
+        ..new_log.clone()
+    };
+    let report = db_state
+        .load_logs(stream::iter([Ok(too_long)]), &params, None)
+        .await;
+    assert!(report.is_err());
+
+    let report = db_state
+        .load_logs(stream::iter([Ok(new_log)]), &params, None)
+        .await
+        .unwrap();
+    assert_eq!(report.accepted, 1);
+    assert!(db_state.delete_log(id).await.unwrap());
+}
@@ -2,11 +2,56 @@ use actix_web::http;
 use actix_web::test;
 use actix_web::web;
 use actix_web::App;
+use async_compression::futures::bufread::BzEncoder;
+use async_compression::futures::bufread::GzipEncoder;
+use async_compression::futures::bufread::ZstdEncoder;
+use futures_util::AsyncReadExt;
+use uuid::Uuid;
 
+use server::db::lines::MAX_LINE_BYTES;
+use server::models::logs::Log;
 use server::scopes::csv::csv_scope;
 
+use api::responses::load::LoadResponse;
+use api::responses::problem::ProblemResponse;
+
 mod mem_db;
 
+fn multipart_csv(csv: &str) -> test::TestRequest {
+    multipart_file("text/csv", csv.as_bytes())
+}
+
+fn multipart_file(content_type: &str, file: &[u8]) -> test::TestRequest {
+    multipart_files(&[(content_type, file)])
+}
+
+fn multipart_files(files: &[(&str, &[u8])]) -> test::TestRequest {
+    let mut bytes = Vec::new();
+    for (content_type, file) in files {
+        let headers = format!(
+            "\r\n\
+            ------WebKitFormBoundary7MA4YWxkTrZu0gW\r\n\
+            Content-Disposition: form-data; name=\"file\"; filename=\"a.csv\"\r\n\
+            Content-Type: {content_type}\r\n\
+            \r\n",
+        );
+        bytes.extend_from_slice(headers.as_bytes());
+        bytes.extend_from_slice(file);
+    }
+    bytes.extend_from_slice(b"\r\n------WebKitFormBoundary7MA4YWxkTrZu0gW--\r\n");
+
+    let header = (
+        http::header::CONTENT_TYPE,
+        http::header::HeaderValue::from_static(
+            r#"multipart/form-data; boundary=----WebKitFormBoundary7MA4YWxkTrZu0gW"#,
+        ),
+    );
+
+    test::TestRequest::post()
+        .append_header(header)
+        .set_payload(bytes)
+}
+
 #[actix_web::test]
 async fn post_csv() {
     let mem_db = mem_db::MemDb::default();
@@ -19,30 +64,464 @@ async fn post_csv() {
     )
     .await;
 
-    let bytes = web::Bytes::from(
-        "\r\n\
-        ------WebKitFormBoundary7MA4YWxkTrZu0gW\r\n\
-        Content-Disposition: form-data; name=\"file\"; filename=\"a.csv\"\r\n\
-        Content-Type: text/csv\r\n
-        \r\n\
-        \"agent a\", 100, 2023-01-02 03:04:07.682066134 UTC\r\n\
-        \"agent b\", 200, 2023-02-03 04:05:09.721651021 UTC\r\n\
-        ------WebKitFormBoundary7MA4YWxkTrZu0gW--\r\n",
+    let req = multipart_csv(
+        "\"agent a\",100,2023-01-02 03:04:07.682066134 UTC\r\n\
+        \"agent b\",200,2023-02-03 04:05:09.721651021 UTC\r\n",
+    )
+    .uri("/csv")
+    .to_request();
+    let res: LoadResponse = test::call_and_read_body_json(&app, req).await;
+
+    assert_eq!(
+        res,
+        LoadResponse {
+            accepted: 2,
+            duplicates: 0,
+            rejected: 0,
+            errors: vec![],
+        }
     );
-    let header = (
-        http::header::CONTENT_TYPE,
-        http::header::HeaderValue::from_static(
-            r#"multipart/form-data; boundary=----WebKitFormBoundary7MA4YWxkTrZu0gW"#,
+}
+
+#[actix_web::test]
+async fn post_csv_with_errors() {
+    let mem_db = mem_db::MemDb::default();
+    let app_state = web::Data::new(mem_db);
+
+    let app = test::init_service(
+        App::new()
+            .app_data(app_state.clone())
+            .configure(csv_scope::<mem_db::MemDb>),
+    )
+    .await;
+
+    let req = multipart_csv(
+        "\"agent a\",100,2023-01-02 03:04:07.682066134 UTC\r\n\
+        \"agent, b\",slow,2023-02-03 04:05:09.721651021 UTC\r\n\
+        \"agent c\",300,yesterday\r\n\
+        \"agent d\",400,2023-04-05 06:07:08.123456789 UTC\r\n",
+    )
+    .uri("/csv?max_errors=1")
+    .to_request();
+    let res: LoadResponse = test::call_and_read_body_json(&app, req).await;
+
+    assert_eq!((res.accepted, res.rejected), (2, 2));
+    assert_eq!(res.errors.len(), 1);
+    assert_eq!(res.errors[0].line, 2);
+    assert_eq!(
+        res.errors[0].raw,
+        "\"agent, b\",slow,2023-02-03 04:05:09.721651021 UTC"
+    );
+}
+
+#[actix_web::test]
+async fn post_csv_with_invalid_logs() {
+    let mem_db = mem_db::MemDb::default();
+    let app_state = web::Data::new(mem_db);
+
+    let app = test::init_service(
+        App::new()
+            .app_data(app_state.clone())
+            .configure(csv_scope::<mem_db::MemDb>),
+    )
+    .await;
+
+    let long = "a".repeat(257);
+    let req = multipart_csv(&format!(
+        "\"agent a\",100\r\n\
+        \" \",-1\r\n\
+        {long},200\r\n"
+    ))
+    .uri("/csv")
+    .to_request();
+    let res: LoadResponse = test::call_and_read_body_json(&app, req).await;
+
+    assert_eq!((res.accepted, res.rejected), (1, 2));
+    assert_eq!(
+        (res.errors[0].line, res.errors[0].error.as_str()),
+        (
+            2,
+            "user_agent: must not be empty; response_time: must not be negative"
+        )
+    );
+    assert_eq!(
+        (res.errors[1].line, res.errors[1].error.as_str()),
+        (3, "user_agent: must be at most 256 characters")
+    );
+}
+
+#[actix_web::test]
+async fn post_csv_with_header() {
+    let cases = [
+        (
+            "/csv",
+            "timestamp,response_time,user_agent\r\n\
+            2023-01-02 03:04:07 UTC,100,\"agent a\"\r\n\
+            2023-02-03 04:05:09 UTC,200,\"agent b\"\r\n",
+        ),
+        (
+            "/csv?columns=user_agent:ua,response_time:latency_ms",
+            "timestamp,ua,latency_ms\r\n\
+            2023-01-02 03:04:07 UTC,\"agent a\",100\r\n\
+            2023-02-03 04:05:09 UTC,\"agent b\",200\r\n",
+        ),
+        (
+            "/csv?columns=user_agent:2,response_time:1,timestamp:0",
+            "2023-01-02 03:04:07 UTC,100,\"agent a\"\r\n\
+            2023-02-03 04:05:09 UTC,200,\"agent b\"\r\n",
+        ),
+    ];
+
+    for (uri, csv) in cases {
+        let mem_db = mem_db::MemDb::default();
+        let app_state = web::Data::new(mem_db);
+
+        let app = test::init_service(
+            App::new()
+                .app_data(app_state.clone())
+                .configure(csv_scope::<mem_db::MemDb>),
+        )
+        .await;
+
+        let req = multipart_csv(csv).uri(uri).to_request();
+        let res: LoadResponse = test::call_and_read_body_json(&app, req).await;
+        assert_eq!((res.accepted, res.rejected), (2, 0), "{uri}");
+
+        let logs = app_state.logs.read().unwrap();
+        let logs = logs
+            .iter()
+            .map(|log| (log.user_agent.as_str(), log.response_time))
+            .collect::<Vec<_>>();
+        assert_eq!(logs, vec![("agent a", 100), ("agent b", 200)], "{uri}");
+    }
+}
+
+// a mapping that doesn't fit the file fails the upload, no row is read
+#[actix_web::test]
+async fn post_csv_with_missing_column() {
+    let cases = [
+        (
+            "/csv?columns=user_agent:ua,response_time:elapsed",
+            "timestamp,ua,latency_ms\r\n\
+            2023-01-02 03:04:07 UTC,\"agent a\",100\r\n",
+            "column `elapsed` is not found in the header",
+        ),
+        (
+            "/csv?columns=user_agent:ua,response_time:latency_ms",
+            "2023-01-02 03:04:07 UTC,\"agent a\",100\r\n\
+            2023-02-03 04:05:09 UTC,\"agent b\",200\r\n",
+            "column `ua` is mapped by name, but the csv has no header",
         ),
+    ];
+
+    for (uri, csv, detail) in cases {
+        let mem_db = mem_db::MemDb::default();
+        let app_state = web::Data::new(mem_db);
+
+        let app = test::init_service(
+            App::new()
+                .app_data(app_state.clone())
+                .configure(csv_scope::<mem_db::MemDb>),
+        )
+        .await;
+
+        let req = multipart_csv(csv).uri(uri).to_request();
+        let res = test::call_service(&app, req).await;
+
+        assert_eq!(res.status(), http::StatusCode::BAD_REQUEST, "{uri}");
+        let problem: ProblemResponse = test::read_body_json(res).await;
+        assert_eq!(problem.detail.as_deref(), Some(detail), "{uri}");
+        assert!(app_state.logs.read().unwrap().is_empty(), "{uri}");
+    }
+}
+
+#[actix_web::test]
+async fn post_compressed_csv() {
+    let csv = "\"agent a\",100,2023-01-02 03:04:07.682066134 UTC\r\n\
+        \"agent b\",200,2023-02-03 04:05:09.721651021 UTC\r\n";
+
+    let mut gzip = Vec::new();
+    GzipEncoder::new(csv.as_bytes())
+        .read_to_end(&mut gzip)
+        .await
+        .unwrap();
+    let mut zstd = Vec::new();
+    ZstdEncoder::new(csv.as_bytes())
+        .read_to_end(&mut zstd)
+        .await
+        .unwrap();
+    let mut bzip2 = Vec::new();
+    BzEncoder::new(csv.as_bytes())
+        .read_to_end(&mut bzip2)
+        .await
+        .unwrap();
+
+    for (content_type, file) in [
+        ("application/gzip", gzip),
+        ("application/zstd", zstd),
+        ("application/x-bzip2", bzip2),
+    ] {
+        let mem_db = mem_db::MemDb::default();
+        let app_state = web::Data::new(mem_db);
+
+        let app = test::init_service(
+            App::new()
+                .app_data(app_state.clone())
+                .configure(csv_scope::<mem_db::MemDb>),
+        )
+        .await;
+
+        let req = multipart_file(content_type, &file).uri("/csv").to_request();
+        let res: LoadResponse = test::call_and_read_body_json(&app, req).await;
+        assert_eq!((res.accepted, res.rejected), (2, 0), "{content_type}");
+    }
+}
+
+// a compressed file that can't be decoded fails the upload, not a row
+#[actix_web::test]
+async fn post_corrupt_compressed_csv() {
+    let csv = "\"agent a\",100,2023-01-02 03:04:07.682066134 UTC\r\n\
+        \"agent b\",200,2023-02-03 04:05:09.721651021 UTC\r\n";
+    let mut gzip = Vec::new();
+    GzipEncoder::new(csv.as_bytes())
+        .read_to_end(&mut gzip)
+        .await
+        .unwrap();
+    let truncated = &gzip[..gzip.len() - 4];
+
+    for file in [b"not gzip at all\r\n".as_slice(), truncated] {
+        let mem_db = mem_db::MemDb::default();
+        let app_state = web::Data::new(mem_db);
+
+        let app = test::init_service(
+            App::new()
+                .app_data(app_state.clone())
+                .configure(csv_scope::<mem_db::MemDb>),
+        )
+        .await;
+
+        let req = multipart_file("application/gzip", file)
+            .uri("/csv")
+            .to_request();
+        let res = test::call_service(&app, req).await;
+
+        assert_eq!(res.status(), http::StatusCode::BAD_REQUEST);
+        let problem: ProblemResponse = test::read_body_json(res).await;
+        assert!(problem
+            .detail
+            .unwrap_or_default()
+            .starts_with("the upload can't be read: "),);
+    }
+}
+
+#[actix_web::test]
+async fn post_csv_in_strict_and_atomic_mode() {
+    let csv = "\"agent a\",100,2023-01-02 03:04:07.682066134 UTC\r\n\
+        \"agent b\",slow,2023-02-03 04:05:09.721651021 UTC\r\n\
+        \"agent c\",300,2023-03-04 05:06:07.890123456 UTC\r\n";
+
+    for (mode, expected_accepted, expected_stored) in [("strict", 1, 1), ("atomic", 0, 0)] {
+        let mem_db = mem_db::MemDb::default();
+        let app_state = web::Data::new(mem_db);
+
+        let app = test::init_service(
+            App::new()
+                .app_data(app_state.clone())
+                .configure(csv_scope::<mem_db::MemDb>),
+        )
+        .await;
+
+        let req = multipart_csv(csv)
+            .uri(&format!("/csv?mode={mode}"))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), http::StatusCode::UNPROCESSABLE_ENTITY);
+
+        let res: LoadResponse = test::read_body_json(res).await;
+        assert_eq!((res.accepted, res.rejected), (expected_accepted, 1));
+        assert_eq!(app_state.logs.read().unwrap().len(), expected_stored);
+    }
+}
+
+// atomicity is per file, the files before the failed one stay committed
+#[actix_web::test]
+async fn post_csv_files_in_strict_and_atomic_mode() {
+    let good = "\"agent a\",100,2023-01-02 03:04:07 UTC\r\n\
+        \"agent b\",200,2023-02-03 04:05:09 UTC\r\n";
+    let bad = "\"agent c\",300,2023-03-04 05:06:07 UTC\r\n\
+        \"agent d\",slow,2023-03-04 05:06:07 UTC\r\n";
+    let last = "\"agent e\",500,2023-04-05 06:07:08 UTC\r\n";
+
+    for (mode, expected_accepted, expected_stored) in [("strict", 3, 3), ("atomic", 2, 2)] {
+        let mem_db = mem_db::MemDb::default();
+        let app_state = web::Data::new(mem_db);
+
+        let app = test::init_service(
+            App::new()
+                .app_data(app_state.clone())
+                .configure(csv_scope::<mem_db::MemDb>),
+        )
+        .await;
+
+        let files = [
+            ("text/csv", good.as_bytes()),
+            ("text/csv", bad.as_bytes()),
+            ("text/csv", last.as_bytes()),
+        ];
+        let req = multipart_files(&files)
+            .uri(&format!("/csv?mode={mode}"))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), http::StatusCode::UNPROCESSABLE_ENTITY);
+
+        let res: LoadResponse = test::read_body_json(res).await;
+        assert_eq!(
+            (res.accepted, res.rejected),
+            (expected_accepted, 1),
+            "{mode}"
+        );
+        let logs = app_state.logs.read().unwrap();
+        assert_eq!(logs.len(), expected_stored, "{mode}");
+        // the file after the failed one is not read
+        assert!(logs.iter().all(|log| log.user_agent != "agent e"), "{mode}");
+    }
+}
+
+// the size of the request is the size of its first file, at most
+#[actix_web::test]
+async fn post_csv_files_with_size_hint() {
+    let file = "\"agent a\",100,2023-01-02 03:04:07 UTC\r\n";
+
+    for count in [1, 3] {
+        let mem_db = mem_db::MemDb::default();
+        let app_state = web::Data::new(mem_db);
+
+        let app = test::init_service(
+            App::new()
+                .app_data(app_state.clone())
+                .configure(csv_scope::<mem_db::MemDb>),
+        )
+        .await;
+        let files = vec![("text/csv", file.as_bytes()); count];
+        let req = multipart_files(&files).uri("/csv").to_request();
+        let length = req
+            .head()
+            .headers
+            .get(http::header::CONTENT_LENGTH)
+            .and_then(|length| length.to_str().ok())
+            .and_then(|length| length.parse::<u64>().ok());
+        let res: LoadResponse = test::call_and_read_body_json(&app, req).await;
+
+        assert_eq!(res.accepted, count as u64);
+        let mut expected = vec![None; count];
+        expected[0] = Some(length.unwrap());
+        assert_eq!(*app_state.size_hints.read().unwrap(), expected);
+    }
+}
+
+#[actix_web::test]
+async fn post_large_csv() {
+    // every 7th row is broken
+    let csv = (0..2500)
+        .map(|i| match i % 7 {
+            6 => format!("\"agent {i}\",broken,2023-01-02 03:04:07 UTC\r\n"),
+            _ => format!("\"agent {i}\",{i},2023-01-02 03:04:07 UTC\r\n"),
+        })
+        .collect::<String>();
+    let rejected = (0..2500).filter(|i| i % 7 == 6).count() as u64;
+
+    for batch_size in [1000, 7, 1] {
+        let mem_db = mem_db::MemDb {
+            batch_size,
+            ..Default::default()
+        };
+        let app_state = web::Data::new(mem_db);
+
+        let app = test::init_service(
+            App::new()
+                .app_data(app_state.clone())
+                .configure(csv_scope::<mem_db::MemDb>),
+        )
+        .await;
+
+        let req = multipart_csv(&csv).uri("/csv").to_request();
+        let res: LoadResponse = test::call_and_read_body_json(&app, req).await;
+
+        assert_eq!((res.accepted, res.rejected), (2500 - rejected, rejected));
+
+        let logs = app_state.logs.read().unwrap();
+        let mut response_times = logs.iter().map(|log| log.response_time).collect::<Vec<_>>();
+        response_times.sort();
+        let expected = (0..2500).filter(|i| i % 7 != 6).collect::<Vec<_>>();
+        assert_eq!(response_times, expected);
+    }
+}
+
+#[actix_web::test]
+async fn post_csv_with_unterminated_quote() {
+    let rows = "agent,100\n".repeat(MAX_LINE_BYTES / 10 + 1);
+    // a quoted field over many lines is one record
+    let quoted = format!("\"agent\n{}\",100\n", "a".repeat(MAX_LINE_BYTES - 20));
+
+    for (csv, status) in [
+        (format!("{quoted}{rows}"), http::StatusCode::OK),
+        (
+            format!("\"agent,100\n{rows}"),
+            http::StatusCode::PAYLOAD_TOO_LARGE,
+        ),
+    ] {
+        let mem_db = mem_db::MemDb::default();
+        let app_state = web::Data::new(mem_db);
+
+        let app = test::init_service(
+            App::new()
+                .app_data(app_state.clone())
+                .configure(csv_scope::<mem_db::MemDb>),
+        )
+        .await;
+        let req = multipart_csv(&csv).uri("/csv").to_request();
+        let res = test::call_service(&app, req).await;
+
+        assert_eq!(res.status(), status);
+    }
+}
+
+#[actix_web::test]
+async fn get_csv() {
+    let log1 = Log {
+        id: Uuid::new_v4(),
+        user_agent: "agent 1".into(),
+        response_time: 100,
+        timestamp: "2023-01-02T03:04:05Z".parse().unwrap(),
+    };
+    let log2 = Log {
+        id: Uuid::new_v4(),
+        user_agent: "agent 2".into(),
+        response_time: 200,
+        timestamp: "2023-02-03T04:05:06Z".parse().unwrap(),
+    };
+
+    let expected = format!(
+        "agent 1,100,2023-01-02T03:04:05Z,{}\n\
+        agent 2,200,2023-02-03T04:05:06Z,{}\n",
+        log1.id, log2.id
     );
 
-    let req = test::TestRequest::post()
-        .uri("/csv")
-        .append_header(header)
-        .set_payload(bytes)
+    let mem_db = mem_db::MemDb::from(vec![log1, log2]);
+    let app_state = web::Data::new(mem_db);
+
+    let app = test::init_service(
+        App::new()
+            .app_data(app_state.clone())
+            .configure(csv_scope::<mem_db::MemDb>),
+    )
+    .await;
+
+    let req = test::TestRequest::get()
+        .uri("/csv?from=2023-01-01T00:00:00Z")
         .to_request();
     let res_body = test::call_and_read_body(&app, req).await;
     let res_str = String::from_utf8(res_body.to_vec()).unwrap();
 
-    assert_eq!(res_str, "2");
+    assert_eq!(res_str, expected);
 }
@@ -0,0 +1,289 @@
+use actix_web::http;
+use actix_web::middleware;
+use actix_web::test;
+use actix_web::web;
+use actix_web::App;
+use actix_web::ResponseError;
+use error_stack::Report;
+use pretty_assertions::assert_eq;
+use uuid::Uuid;
+
+use server::errors::AppError;
+use server::errors::AppResponseError;
+use server::request_id::request_id;
+use server::scopes::csv::csv_scope;
+use server::scopes::ingest::ingest_scope;
+use server::scopes::logs::logs_scope;
+use server::scopes::stats::stats_scope;
+use server::scopes::UploadLimits;
+
+use api::responses::problem::ProblemResponse;
+
+mod mem_db;
+
+#[actix_web::test]
+async fn validation_errors() {
+    let mem_db = mem_db::MemDb::default();
+    let app_state = web::Data::new(mem_db);
+
+    let app = test::init_service(
+        App::new()
+            .app_data(app_state.clone())
+            .configure(csv_scope::<mem_db::MemDb>)
+            .configure(logs_scope::<mem_db::MemDb>)
+            .configure(stats_scope::<mem_db::MemDb>),
+    )
+    .await;
+    let reqs = [
+        test::TestRequest::post()
+            .uri("/logs")
+            .append_header(http::header::ContentType::json())
+            .set_payload("{\"user_agent\": ")
+            .to_request(),
+        test::TestRequest::get()
+            .uri("/logs?from=2023-01-02T00:00:00Z&until=2023-01-01T00:00:00Z")
+            .to_request(),
+        test::TestRequest::get()
+            .uri("/stats?from=2023-01-02T00:00:00Z&until=2023-01-01T00:00:00Z")
+            .to_request(),
+        // a multipart body without its boundary
+        test::TestRequest::post()
+            .uri("/csv")
+            .append_header((http::header::CONTENT_TYPE, "multipart/form-data"))
+            .set_payload("user_agent,response_time\nagent 1,100\n")
+            .to_request(),
+    ];
+    for req in reqs {
+        let uri = req.uri().to_string();
+        let res = test::call_service(&app, req).await;
+
+        assert_eq!(res.status(), http::StatusCode::BAD_REQUEST, "{uri}");
+        assert_eq!(
+            res.headers().get(http::header::CONTENT_TYPE).unwrap(),
+            "application/problem+json",
+            "{uri}"
+        );
+        let problem: ProblemResponse = test::read_body_json(res).await;
+        assert_eq!(
+            (problem.status, problem.title.as_str()),
+            (400, "Bad Request")
+        );
+        assert!(problem.detail.is_some(), "{uri}");
+    }
+}
+
+#[actix_web::test]
+async fn not_found() {
+    let mem_db = mem_db::MemDb::default();
+    let app_state = web::Data::new(mem_db);
+
+    let app = test::init_service(
+        App::new()
+            .app_data(app_state.clone())
+            .configure(logs_scope::<mem_db::MemDb>),
+    )
+    .await;
+    let req = test::TestRequest::get()
+        .uri(&format!("/logs/{}", Uuid::new_v4()))
+        .to_request();
+    let problem: ProblemResponse = test::call_and_read_body_json(&app, req).await;
+
+    assert_eq!(
+        problem,
+        ProblemResponse {
+            problem_type: "about:blank".into(),
+            title: "Not Found".into(),
+            status: 404,
+            detail: Some("not found".into()),
+            request_id: None,
+            errors: vec![],
+        }
+    );
+}
+
+#[actix_web::test]
+async fn payload_too_large() {
+    let mem_db = mem_db::MemDb::default();
+    let app_state = web::Data::new(mem_db);
+
+    let app = test::init_service(
+        App::new()
+            .app_data(app_state.clone())
+            .configure(logs_scope::<mem_db::MemDb>),
+    )
+    .await;
+    let req = test::TestRequest::post()
+        .uri("/logs")
+        .append_header(http::header::ContentType::json())
+        .set_payload(vec![b' '; 64 * 1024 * 1024 + 1])
+        .to_request();
+    let problem: ProblemResponse = test::call_and_read_body_json(&app, req).await;
+
+    assert_eq!(
+        (problem.status, problem.title.as_str()),
+        (413, "Payload Too Large")
+    );
+}
+
+#[actix_web::test]
+async fn upload_limits() {
+    let mem_db = mem_db::MemDb::default();
+    let app_state = web::Data::new(mem_db);
+    let limits = UploadLimits {
+        json_body: 128,
+        log_body: 64,
+        upload: Some(64),
+    };
+
+    let app = test::init_service(
+        App::new()
+            .app_data(app_state.clone())
+            .app_data(limits)
+            .configure(ingest_scope::<mem_db::MemDb>)
+            .configure(logs_scope::<mem_db::MemDb>),
+    )
+    .await;
+    let log = "{\"user_agent\":\"agent 1\",\"response_time\":100}\n";
+    let reqs = [
+        test::TestRequest::post()
+            .uri("/logs")
+            .append_header(http::header::ContentType::json())
+            .set_payload(format!("{log:<65}")),
+        test::TestRequest::post()
+            .uri("/logs/bulk")
+            .append_header((http::header::CONTENT_TYPE, "application/x-ndjson"))
+            .set_payload(log.repeat(2)),
+        test::TestRequest::post()
+            .uri("/logs/bulk")
+            .append_header(http::header::ContentType::json())
+            .set_payload(format!("[{:<128}]", log.trim_end())),
+        test::TestRequest::post()
+            .uri("/ingest/access-log?format=nginx")
+            .set_payload(vec![b'\n'; 65]),
+    ];
+    for req in reqs {
+        let req = req.to_request();
+        let uri = req.uri().to_string();
+        let res = test::call_service(&app, req).await;
+
+        assert_eq!(res.status(), http::StatusCode::PAYLOAD_TOO_LARGE, "{uri}");
+        let problem: ProblemResponse = test::read_body_json(res).await;
+        assert_eq!(problem.status, 413, "{uri}");
+    }
+
+    // within the limits
+    let req = test::TestRequest::post()
+        .uri("/logs/bulk")
+        .append_header((http::header::CONTENT_TYPE, "application/x-ndjson"))
+        .set_payload(log)
+        .to_request();
+    let res = test::call_service(&app, req).await;
+
+    assert!(res.status().is_success());
+}
+
+#[actix_web::test]
+async fn unavailable() {
+    let mem_db = mem_db::MemDb {
+        unavailable: true,
+        ..Default::default()
+    };
+    let app_state = web::Data::new(mem_db);
+
+    let app = test::init_service(
+        App::new()
+            .app_data(app_state.clone())
+            .configure(logs_scope::<mem_db::MemDb>),
+    )
+    .await;
+    let req = test::TestRequest::get().uri("/logs").to_request();
+    let res = test::call_service(&app, req).await;
+
+    assert_eq!(res.status(), http::StatusCode::SERVICE_UNAVAILABLE);
+    assert!(res.headers().contains_key(http::header::RETRY_AFTER));
+    let problem: ProblemResponse = test::read_body_json(res).await;
+    assert_eq!(problem.detail.unwrap(), "the service is unavailable");
+}
+
+#[actix_web::test]
+async fn database_errors() {
+    for (error, status) in [
+        (
+            sqlx::Error::PoolTimedOut,
+            http::StatusCode::SERVICE_UNAVAILABLE,
+        ),
+        (
+            sqlx::Error::PoolClosed,
+            http::StatusCode::SERVICE_UNAVAILABLE,
+        ),
+        (
+            sqlx::Error::RowNotFound,
+            http::StatusCode::INTERNAL_SERVER_ERROR,
+        ),
+        (
+            sqlx::Error::Protocol("bad message".into()),
+            http::StatusCode::INTERNAL_SERVER_ERROR,
+        ),
+    ] {
+        let report = Report::new(error).change_context(AppError);
+        let error = AppResponseError::from(report);
+
+        assert_eq!(error.status_code(), status, "{error}");
+    }
+
+    // other errors are internal, their details stay in the server log
+    let report = Report::new(AppError);
+    let res = AppResponseError::from(report).error_response();
+    let body = actix_web::body::to_bytes(res.into_body()).await.unwrap();
+    let problem: ProblemResponse = serde_json::from_slice(&body).unwrap();
+
+    assert_eq!(
+        (problem.status, problem.detail.unwrap().as_str()),
+        (500, "internal error")
+    );
+}
+
+#[actix_web::test]
+async fn problems_with_request_id() {
+    let mem_db = mem_db::MemDb::default();
+    let app_state = web::Data::new(mem_db);
+
+    let app = test::init_service(
+        App::new()
+            .wrap(middleware::from_fn(request_id))
+            .app_data(app_state.clone())
+            .configure(logs_scope::<mem_db::MemDb>),
+    )
+    .await;
+
+    // extractor errors are problems too
+    let req = test::TestRequest::get()
+        .uri("/logs?limit=many")
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), http::StatusCode::BAD_REQUEST);
+    assert_eq!(
+        res.headers().get(http::header::CONTENT_TYPE).unwrap(),
+        "application/problem+json"
+    );
+    let id = res.headers().get("x-request-id").unwrap().clone();
+    let problem: ProblemResponse = test::read_body_json(res).await;
+    assert_eq!(problem.request_id.unwrap(), id.to_str().unwrap());
+
+    // the id of the client is kept
+    let req = test::TestRequest::get()
+        .uri(&format!("/logs/{}", Uuid::new_v4()))
+        .append_header(("X-Request-Id", "client-id-1"))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.headers().get("x-request-id").unwrap(), "client-id-1");
+    let problem: ProblemResponse = test::read_body_json(res).await;
+    assert_eq!(problem.status, 404);
+    assert_eq!(problem.request_id.unwrap(), "client-id-1");
+
+    // and successful responses have one
+    let req = test::TestRequest::get().uri("/logs").to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), http::StatusCode::OK);
+    assert!(res.headers().contains_key("x-request-id"));
+}
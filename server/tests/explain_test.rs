@@ -0,0 +1,78 @@
+use sqlx::PgPool;
+use sqlx::QueryBuilder;
+
+use server::db::query::push_select_logs;
+
+use api::params::Cursor;
+use api::params::LogFilter;
+use api::params::UserAgentMatch;
+
+mod pg_db;
+
+// the test tables are small, so sequential scans are ruled out to see which indexes are usable
+async fn explain(pool: &PgPool, mut query: QueryBuilder<'_, sqlx::Postgres>) -> String {
+    let mut tx = pool.begin().await.unwrap();
+    sqlx::query("SET LOCAL enable_seqscan = off")
+        .execute(&mut tx)
+        .await
+        .unwrap();
+
+    let plan: Vec<(String,)> = query.build_query_as().fetch_all(&mut tx).await.unwrap();
+    plan.into_iter()
+        .map(|(line,)| line)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[actix_web::test]
+async fn time_range_uses_index() {
+    let db_state = pg_db::connect(1).await;
+
+    let from = "2023-01-01T00:00:00Z".parse().unwrap();
+    let cursor = Cursor {
+        timestamp: "2023-01-02T00:00:00Z".parse().unwrap(),
+        id: uuid::Uuid::nil(),
+    };
+    for (until, cursor) in [(None, None), (Some(from), Some(cursor))] {
+        let mut query = QueryBuilder::new("EXPLAIN ");
+        push_select_logs(
+            &mut query,
+            Some(from),
+            until,
+            &LogFilter::default(),
+            cursor,
+            Some(100),
+        );
+        let plan = explain(&db_state, query).await;
+
+        assert!(plan.contains("timestamp_id_idx"), "{plan}");
+        assert!(
+            plan.lines()
+                .any(|line| line.contains("Index Cond:") && line.contains("\"timestamp\" >=")),
+            "{plan}"
+        );
+        assert!(!plan.contains("Seq Scan"), "{plan}");
+    }
+}
+
+#[actix_web::test]
+async fn user_agent_prefix_uses_index() {
+    let db_state = pg_db::connect(1).await;
+
+    for user_agent_match in [UserAgentMatch::Exact, UserAgentMatch::Prefix] {
+        let filter = LogFilter {
+            user_agent: Some("agent".into()),
+            user_agent_match,
+            ..LogFilter::default()
+        };
+        let mut query = QueryBuilder::new("EXPLAIN ");
+        push_select_logs(&mut query, None, None, &filter, None, None);
+        let plan = explain(&db_state, query).await;
+
+        assert!(
+            plan.contains("user_agent_idx"),
+            "{user_agent_match:?}\n{plan}"
+        );
+        assert!(!plan.contains("Seq Scan"), "{user_agent_match:?}\n{plan}");
+    }
+}
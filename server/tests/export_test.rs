@@ -0,0 +1,104 @@
+use std::io::Cursor;
+
+use actix_web::http;
+use actix_web::test;
+use actix_web::web;
+use actix_web::App;
+use arrow_array::cast::AsArray;
+use arrow_array::types::Int32Type;
+use arrow_array::types::TimestampMicrosecondType;
+use arrow_array::RecordBatch;
+use arrow_ipc::reader::FileReader;
+use arrow_schema::DataType;
+use arrow_schema::TimeUnit;
+use chrono::Duration;
+use chrono::SubsecRound;
+use chrono::Utc;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use pretty_assertions::assert_eq;
+use uuid::Uuid;
+
+use server::models::logs::Log;
+use server::scopes::export::export_scope;
+
+mod mem_db;
+
+#[actix_web::test]
+async fn get_export() {
+    let now = Utc::now().trunc_subsecs(6);
+    let logs = (0..3)
+        .map(|i| Log {
+            id: Uuid::new_v4(),
+            user_agent: format!("agent {i}"),
+            response_time: i * 100,
+            timestamp: now - Duration::seconds(3 - i as i64),
+        })
+        .collect::<Vec<_>>();
+
+    for format in ["parquet", "arrow"] {
+        let mem_db = mem_db::MemDb::from(logs.clone());
+        let app_state = web::Data::new(mem_db);
+
+        let app = test::init_service(
+            App::new()
+                .app_data(app_state.clone())
+                .configure(export_scope::<mem_db::MemDb>),
+        )
+        .await;
+        let req = test::TestRequest::get()
+            .uri(&format!("/export?format={format}"))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), http::StatusCode::OK, "{format}");
+        let body = test::read_body(res).await;
+
+        let batches = match format {
+            "parquet" => ParquetRecordBatchReaderBuilder::try_new(body)
+                .unwrap()
+                .build()
+                .unwrap()
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap(),
+            _ => FileReader::try_new(Cursor::new(body), None)
+                .unwrap()
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap(),
+        };
+        let schema = batches[0].schema();
+        let types = schema
+            .fields()
+            .iter()
+            .map(|field| field.data_type().clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            types,
+            vec![
+                DataType::FixedSizeBinary(16),
+                DataType::Utf8,
+                DataType::Int32,
+                DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())),
+            ],
+            "{format}"
+        );
+
+        let exported = batches.iter().flat_map(batch_to_logs).collect::<Vec<_>>();
+
+        assert_eq!(exported, logs, "{format}");
+    }
+}
+
+fn batch_to_logs(batch: &RecordBatch) -> Vec<Log> {
+    let ids = batch.column(0).as_fixed_size_binary();
+    let user_agents = batch.column(1).as_string::<i32>();
+    let response_times = batch.column(2).as_primitive::<Int32Type>();
+    let timestamps = batch.column(3).as_primitive::<TimestampMicrosecondType>();
+
+    (0..batch.num_rows())
+        .map(|i| Log {
+            id: Uuid::from_slice(ids.value(i)).unwrap(),
+            user_agent: user_agents.value(i).to_string(),
+            response_time: response_times.value(i),
+            timestamp: timestamps.value_as_datetime(i).unwrap().and_utc(),
+        })
+        .collect()
+}
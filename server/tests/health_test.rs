@@ -0,0 +1,138 @@
+use actix_web::http;
+use actix_web::test;
+use actix_web::web;
+use actix_web::App;
+use pretty_assertions::assert_eq;
+
+use server::models::health::PoolStatus;
+use server::scopes::health::health_scope;
+
+use api::responses::health::HealthResponse;
+use api::responses::health::PoolResponse;
+use api::responses::health::ReadinessResponse;
+use api::responses::health::VersionResponse;
+
+mod mem_db;
+
+#[actix_web::test]
+async fn healthz() {
+    // alive, even without the database
+    let mem_db = mem_db::MemDb {
+        unavailable: true,
+        ..Default::default()
+    };
+    let app_state = web::Data::new(mem_db);
+
+    let app = test::init_service(
+        App::new()
+            .app_data(app_state.clone())
+            .configure(health_scope::<mem_db::MemDb>),
+    )
+    .await;
+    let req = test::TestRequest::get().uri("/healthz").to_request();
+    let res: HealthResponse = test::call_and_read_body_json(&app, req).await;
+
+    assert_eq!(res.status, "ok");
+}
+
+#[actix_web::test]
+async fn readyz() {
+    let mem_db = mem_db::MemDb::default();
+    let app_state = web::Data::new(mem_db);
+
+    let app = test::init_service(
+        App::new()
+            .app_data(app_state.clone())
+            .configure(health_scope::<mem_db::MemDb>),
+    )
+    .await;
+    let req = test::TestRequest::get().uri("/readyz").to_request();
+    let res = test::call_service(&app, req).await;
+
+    assert_eq!(res.status(), http::StatusCode::OK);
+    let res: ReadinessResponse = test::read_body_json(res).await;
+    assert_eq!(
+        res,
+        ReadinessResponse {
+            ready: true,
+            database: true,
+            pool: PoolResponse {
+                size: 1,
+                idle: 1,
+                max_connections: 10,
+                saturated: false,
+            },
+        }
+    );
+}
+
+#[actix_web::test]
+async fn readyz_with_unhealthy_database() {
+    let unavailable = mem_db::MemDb {
+        unavailable: true,
+        ..Default::default()
+    };
+    let saturated = mem_db::MemDb {
+        pool_status: PoolStatus {
+            size: 10,
+            idle: 0,
+            max_connections: 10,
+        },
+        ..Default::default()
+    };
+
+    for (mem_db, saturated) in [(unavailable, false), (saturated, true)] {
+        let app_state = web::Data::new(mem_db);
+
+        let app = test::init_service(
+            App::new()
+                .app_data(app_state.clone())
+                .configure(health_scope::<mem_db::MemDb>),
+        )
+        .await;
+        let req = test::TestRequest::get().uri("/readyz").to_request();
+        let res = test::call_service(&app, req).await;
+
+        assert_eq!(res.status(), http::StatusCode::SERVICE_UNAVAILABLE);
+        let res: ReadinessResponse = test::read_body_json(res).await;
+        assert_eq!((res.ready, res.database), (false, false));
+        assert_eq!(res.pool.saturated, saturated);
+    }
+}
+
+#[actix_web::test]
+async fn version() {
+    let mem_db = mem_db::MemDb::default();
+    let app_state = web::Data::new(mem_db);
+
+    let app = test::init_service(
+        App::new()
+            .app_data(app_state.clone())
+            .configure(health_scope::<mem_db::MemDb>),
+    )
+    .await;
+    let req = test::TestRequest::get().uri("/version").to_request();
+    let res: VersionResponse = test::call_and_read_body_json(&app, req).await;
+
+    assert_eq!(res.version, env!("CARGO_PKG_VERSION"));
+    assert_eq!(res.migration_version, Some(20230604003127));
+
+    // the version without the database
+    let mem_db = mem_db::MemDb {
+        unavailable: true,
+        ..Default::default()
+    };
+    let app_state = web::Data::new(mem_db);
+
+    let app = test::init_service(
+        App::new()
+            .app_data(app_state.clone())
+            .configure(health_scope::<mem_db::MemDb>),
+    )
+    .await;
+    let req = test::TestRequest::get().uri("/version").to_request();
+    let res: VersionResponse = test::call_and_read_body_json(&app, req).await;
+
+    assert_eq!(res.version, env!("CARGO_PKG_VERSION"));
+    assert_eq!(res.migration_version, None);
+}
@@ -0,0 +1,57 @@
+use pretty_assertions::assert_eq;
+use uuid::Uuid;
+
+use server::db::DbTrait;
+use server::models::idempotency::IdempotencyClaim;
+use server::models::idempotency::StoredResponse;
+
+mod pg_db;
+
+// a key is replayed only to the request that claimed it
+#[actix_web::test]
+async fn claim_idempotency_key_in_postgres() {
+    let db_state = pg_db::connect(1).await;
+    let key = Uuid::new_v4().to_string();
+    let response = StoredResponse {
+        status: 201,
+        content_type: Some("application/json".into()),
+        body: b"{}".to_vec(),
+    };
+
+    let claim = db_state
+        .claim_idempotency_key(&key, b"first")
+        .await
+        .unwrap();
+    assert_eq!(claim, IdempotencyClaim::Claimed);
+    let claim = db_state
+        .claim_idempotency_key(&key, b"first")
+        .await
+        .unwrap();
+    assert_eq!(claim, IdempotencyClaim::InProgress);
+    let claim = db_state
+        .claim_idempotency_key(&key, b"second")
+        .await
+        .unwrap();
+    assert_eq!(claim, IdempotencyClaim::Mismatch);
+
+    db_state
+        .complete_idempotency_key(&key, &response)
+        .await
+        .unwrap();
+    let claim = db_state
+        .claim_idempotency_key(&key, b"first")
+        .await
+        .unwrap();
+    assert_eq!(claim, IdempotencyClaim::Completed(response));
+    let claim = db_state
+        .claim_idempotency_key(&key, b"second")
+        .await
+        .unwrap();
+    assert_eq!(claim, IdempotencyClaim::Mismatch);
+
+    sqlx::query("DELETE FROM idempotency_keys WHERE key = $1")
+        .bind(&key)
+        .execute(&*db_state)
+        .await
+        .unwrap();
+}
@@ -0,0 +1,125 @@
+use std::time;
+
+use actix_web::rt;
+use chrono::DateTime;
+use chrono::Duration;
+use chrono::Utc;
+use futures_util::future;
+use futures_util::stream;
+use futures_util::StreamExt;
+use pretty_assertions::assert_eq;
+use uuid::Uuid;
+
+use server::db::DbTrait;
+use server::models::logs::InsertedLog;
+use server::states::DbState;
+
+use api::params::ImportMode;
+use api::params::IngestMethod;
+use api::params::LoadParams;
+use api::requests::logs::NewLog;
+
+mod pg_db;
+
+fn utc(timestamp: &str) -> DateTime<Utc> {
+    timestamp.parse().unwrap()
+}
+
+// opening a connection takes a while, they are opened first so that the requests run together
+async fn open_connections(db_state: &DbState, n: usize) {
+    let conns = future::try_join_all((0..n).map(|_| db_state.acquire()))
+        .await
+        .unwrap();
+    drop(conns);
+}
+
+async fn count_logs(db_state: &DbState, ids: &[Uuid]) -> i64 {
+    sqlx::query_scalar("SELECT COUNT(*) FROM logs WHERE id = ANY($1)")
+        .bind(ids)
+        .fetch_one(&**db_state)
+        .await
+        .unwrap()
+}
+
+// the same id sent at the same time, with different timestamps, is stored once
+#[actix_web::test]
+async fn insert_the_same_id_concurrently() {
+    let db_state = pg_db::connect(10).await;
+    open_connections(&db_state, 10).await;
+
+    let id = Uuid::new_v4();
+    let start = utc("1984-01-01T00:00:00Z");
+    let inserts = (0..10).map(|minutes| {
+        let timestamp = start + Duration::minutes(minutes);
+        db_state.insert_log("agent", 100, Some(timestamp), Some(id))
+    });
+    let inserted = future::try_join_all(inserts).await.unwrap();
+    let new_logs = inserted
+        .iter()
+        .filter(|log| matches!(log, InsertedLog::New(_)))
+        .count();
+    let logs = inserted
+        .into_iter()
+        .map(InsertedLog::into_log)
+        .collect::<Vec<_>>();
+
+    // every request gets the log that was stored, by one of them
+    assert_eq!(new_logs, 1);
+    assert!(logs.iter().all(|log| *log == logs[0]), "{logs:?}");
+    assert_eq!(count_logs(&db_state, &[id]).await, 1);
+    assert!(db_state.delete_log(id).await.unwrap());
+}
+
+// the rows of an atomic load are not committed until the upload ends, the other loads must
+// still skip their ids
+#[actix_web::test]
+async fn load_the_same_ids_concurrently() {
+    let ids = (0..50).map(|_| Uuid::new_v4()).collect::<Vec<_>>();
+    // the rows are written in one batch as soon as they are read
+    let db_state = pg_db::connect(10).await.with_batch_size(ids.len());
+    open_connections(&db_state, 6).await;
+    let start = utc("1984-02-01T00:00:00Z");
+    let loads = [IngestMethod::Unnest, IngestMethod::Copy]
+        .into_iter()
+        .cycle()
+        .take(6)
+        .zip(0..)
+        .map(|(method, load)| {
+            let new_logs = ids
+                .iter()
+                .map(|id| {
+                    Ok(NewLog {
+                        user_agent: format!("load {load}"),
+                        response_time: 100,
+                        timestamp: Some(start + Duration::minutes(load)),
+                        id: Some(*id),
+                    })
+                })
+                .collect::<Vec<_>>();
+            let params = LoadParams {
+                mode: ImportMode::Atomic,
+                ingest: Some(method),
+                ..Default::default()
+            };
+            // the upload goes on for a while after its rows, with the transaction open
+            let slow_end = stream::once(async {
+                rt::time::sleep(time::Duration::from_millis(200)).await;
+            })
+            .filter_map(|_| future::ready(None));
+            let new_logs = stream::iter(new_logs).chain(slow_end);
+            let db_state = &db_state;
+            async move { db_state.load_logs(new_logs, &params, None).await }
+        });
+    let reports = future::try_join_all(loads).await.unwrap();
+
+    let accepted = reports.iter().map(|report| report.accepted).sum::<u64>();
+    let duplicates = reports.iter().map(|report| report.duplicates).sum::<u64>();
+    assert_eq!((accepted, duplicates), (50, 250));
+    assert_eq!(count_logs(&db_state, &ids).await, 50);
+
+    let deleted = db_state
+        .delete_logs(Some(start), Some(start + Duration::minutes(5)))
+        .await
+        .unwrap();
+    assert_eq!(deleted, 50);
+}
@@ -0,0 +1,80 @@
+use actix_web::http;
+use actix_web::test;
+use actix_web::web;
+use actix_web::App;
+use chrono::TimeZone;
+use chrono::Utc;
+use pretty_assertions::assert_eq;
+
+use server::scopes::ingest::ingest_scope;
+
+use api::responses::load::LoadResponse;
+
+mod mem_db;
+
+#[actix_web::test]
+async fn post_access_log() {
+    let common = r#"127.0.0.1 - frank [10/Oct/2000:13:55:36 -0700] "GET /apache_pb.gif HTTP/1.0" 200 2326 1500"#;
+    let combined = r#"127.0.0.1 - - [10/Oct/2000:13:55:36 -0700] "GET / HTTP/1.0" 200 2326 "http://example.com/" "Mozilla/4.08 [en] (Win98; I ;Nav)" 1500"#;
+    let nginx = r#"10.0.0.1 - - [10/Oct/2000:20:55:36 +0000] "GET / HTTP/1.1" 200 612 "-" "curl/8.0 \"quoted\" \x22hex\x22" rt=0.0015"#;
+
+    for (format, line, user_agent) in [
+        ("common", common, "-"),
+        ("combined", combined, "Mozilla/4.08 [en] (Win98; I ;Nav)"),
+        ("nginx", nginx, r#"curl/8.0 "quoted" "hex""#),
+    ] {
+        let mem_db = mem_db::MemDb::default();
+        let app_state = web::Data::new(mem_db);
+
+        let app = test::init_service(
+            App::new()
+                .app_data(app_state.clone())
+                .configure(ingest_scope::<mem_db::MemDb>),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri(&format!("/ingest/access-log?format={format}"))
+            .set_payload(format!("{line}\nnot an access log\n"))
+            .to_request();
+        let res: LoadResponse = test::call_and_read_body_json(&app, req).await;
+
+        assert_eq!((res.accepted, res.rejected), (1, 1), "{format}");
+        assert_eq!(res.errors[0].line, 2, "{format}");
+
+        let logs = app_state.logs.read().unwrap();
+        assert_eq!(logs[0].user_agent, user_agent, "{format}");
+        assert_eq!(logs[0].response_time, 2, "{format}");
+        assert_eq!(
+            logs[0].timestamp,
+            Utc.with_ymd_and_hms(2000, 10, 10, 20, 55, 36).unwrap(),
+            "{format}"
+        );
+    }
+}
+
+#[actix_web::test]
+async fn post_access_log_in_strict_mode() {
+    let mem_db = mem_db::MemDb::default();
+    let app_state = web::Data::new(mem_db);
+
+    let app = test::init_service(
+        App::new()
+            .app_data(app_state.clone())
+            .configure(ingest_scope::<mem_db::MemDb>),
+    )
+    .await;
+
+    // no response time
+    let req = test::TestRequest::post()
+        .uri("/ingest/access-log?format=nginx&mode=strict")
+        .set_payload(
+            r#"10.0.0.1 - - [10/Oct/2000:20:55:36 +0000] "GET / HTTP/1.1" 200 612 "-" "curl/8.0""#,
+        )
+        .to_request();
+    let res = test::call_service(&app, req).await;
+
+    assert_eq!(res.status(), http::StatusCode::UNPROCESSABLE_ENTITY);
+    let res: LoadResponse = test::read_body_json(res).await;
+    assert_eq!(res.errors[0].error, "missing response time");
+}
@@ -2,16 +2,25 @@ use actix_web::http;
 use actix_web::test;
 use actix_web::web;
 use actix_web::App;
+use async_compression::futures::bufread::GzipEncoder;
+use chrono::Duration;
 use chrono::SubsecRound;
 use chrono::Utc;
+use futures_util::AsyncReadExt;
 use pretty_assertions::assert_eq;
 use uuid::Uuid;
 
+use server::db::lines::MAX_LINE_BYTES;
 use server::models::logs::Log;
 use server::scopes::logs::logs_scope;
 
+use api::params::Cursor;
 use api::requests::logs::NewLog;
+use api::responses::load::LoadResponse;
+use api::responses::logs::DeleteResponse;
 use api::responses::logs::LogResponse;
+use api::responses::logs::LogsResponse;
+use api::responses::problem::ProblemResponse;
 
 mod mem_db;
 
@@ -33,11 +42,453 @@ async fn create_logs() {
             user_agent: "Agent 1".into(),
             response_time: 100,
             timestamp: None,
+            id: None,
         })
         .to_request();
     let res: LogResponse = test::call_and_read_body_json(&app, req).await;
 
     assert_eq!(res.user_agent, "Agent 1");
+    assert_eq!(res.id, app_state.logs.read().unwrap()[0].id);
+}
+
+#[actix_web::test]
+async fn create_bulk_logs() {
+    let ndjson = "{\"user_agent\":\"agent 1\",\"response_time\":100}\n\
+        \n\
+        {\"user_agent\":\"agent 2\",\"response_time\":\"slow\"}\n\
+        {\"user_agent\":\"agent 3\",\"response_time\":300,\"timestamp\":\"2023-01-02T03:04:05Z\"}\n";
+    let json = r#"[
+        {"user_agent": "agent 1", "response_time": 100},
+        {"user_agent": "agent 2"},
+        {"user_agent": "agent 3", "response_time": 300}
+    ]"#;
+
+    for (content_type, body, error_line) in [
+        ("application/x-ndjson", ndjson, 3),
+        ("application/json", json, 2),
+    ] {
+        let mem_db = mem_db::MemDb::default();
+        let app_state = web::Data::new(mem_db);
+
+        let app = test::init_service(
+            App::new()
+                .app_data(app_state.clone())
+                .configure(logs_scope::<mem_db::MemDb>),
+        )
+        .await;
+        let req = test::TestRequest::post()
+            .uri("/logs/bulk")
+            .append_header((http::header::CONTENT_TYPE, content_type))
+            .set_payload(body)
+            .to_request();
+        let res: LoadResponse = test::call_and_read_body_json(&app, req).await;
+
+        assert_eq!((res.accepted, res.rejected), (2, 1), "{content_type}");
+        assert_eq!(res.errors[0].line, error_line, "{content_type}");
+
+        let logs = app_state.logs.read().unwrap();
+        let user_agents = logs
+            .iter()
+            .map(|log| log.user_agent.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(user_agents, vec!["agent 1", "agent 3"], "{content_type}");
+    }
+}
+
+#[actix_web::test]
+async fn create_bulk_logs_with_bad_body() {
+    let mem_db = mem_db::MemDb::default();
+    let app_state = web::Data::new(mem_db);
+
+    let app = test::init_service(
+        App::new()
+            .app_data(app_state.clone())
+            .configure(logs_scope::<mem_db::MemDb>),
+    )
+    .await;
+
+    for (content_type, body, status) in [
+        ("application/json", "{}", http::StatusCode::BAD_REQUEST),
+        (
+            "text/plain",
+            "agent 1",
+            http::StatusCode::UNSUPPORTED_MEDIA_TYPE,
+        ),
+    ] {
+        let req = test::TestRequest::post()
+            .uri("/logs/bulk")
+            .append_header((http::header::CONTENT_TYPE, content_type))
+            .set_payload(body)
+            .to_request();
+        let res = test::call_service(&app, req).await;
+
+        assert_eq!(res.status(), status, "{content_type}");
+    }
+}
+
+// a line at the limit is only a bad row, a longer one is not read to its end
+#[actix_web::test]
+async fn create_bulk_logs_with_long_line() {
+    let mem_db = mem_db::MemDb::default();
+    let app_state = web::Data::new(mem_db);
+
+    let app = test::init_service(
+        App::new()
+            .app_data(app_state.clone())
+            .configure(logs_scope::<mem_db::MemDb>),
+    )
+    .await;
+
+    for (length, status) in [
+        (MAX_LINE_BYTES, http::StatusCode::OK),
+        (MAX_LINE_BYTES + 1, http::StatusCode::PAYLOAD_TOO_LARGE),
+    ] {
+        let body = format!(
+            "{{\"user_agent\":\"agent 1\",\"response_time\":100}}\n{}\n",
+            "a".repeat(length)
+        );
+        let req = test::TestRequest::post()
+            .uri("/logs/bulk")
+            .append_header((http::header::CONTENT_TYPE, "application/x-ndjson"))
+            .set_payload(body)
+            .to_request();
+        let res = test::call_service(&app, req).await;
+
+        assert_eq!(res.status(), status, "{length}");
+    }
+}
+
+#[actix_web::test]
+async fn create_logs_as_csv_or_ndjson() {
+    let csv = "user_agent,response_time\nagent 1,100\nagent 2,200\n";
+    let ndjson = "{\"user_agent\":\"agent 1\",\"response_time\":100}\n\
+        {\"user_agent\":\"agent 2\",\"response_time\":200}\n";
+
+    for (content_type, body) in [("text/csv", csv), ("application/x-ndjson", ndjson)] {
+        let mem_db = mem_db::MemDb::default();
+        let app_state = web::Data::new(mem_db);
+
+        let app = test::init_service(
+            App::new()
+                .app_data(app_state.clone())
+                .configure(logs_scope::<mem_db::MemDb>),
+        )
+        .await;
+        let req = test::TestRequest::post()
+            .uri("/logs")
+            .append_header((http::header::CONTENT_TYPE, content_type))
+            .set_payload(body)
+            .to_request();
+        let res: LoadResponse = test::call_and_read_body_json(&app, req).await;
+
+        assert_eq!((res.accepted, res.rejected), (2, 0), "{content_type}");
+        assert_eq!(app_state.logs.read().unwrap().len(), 2, "{content_type}");
+    }
+}
+
+#[actix_web::test]
+async fn create_compressed_logs() {
+    let json = r#"{"user_agent": "agent 1", "response_time": 100}"#;
+    let ndjson = "{\"user_agent\":\"agent 1\",\"response_time\":100}\n\
+        {\"user_agent\":\"agent 2\",\"response_time\":200}\n";
+
+    for (uri, content_type, body, count) in [
+        ("/logs", "application/json", json, 1),
+        ("/logs", "application/x-ndjson", ndjson, 2),
+        ("/logs/bulk", "application/x-ndjson", ndjson, 2),
+    ] {
+        let mut gzip = Vec::new();
+        GzipEncoder::new(body.as_bytes())
+            .read_to_end(&mut gzip)
+            .await
+            .unwrap();
+
+        let mem_db = mem_db::MemDb::default();
+        let app_state = web::Data::new(mem_db);
+
+        let app = test::init_service(
+            App::new()
+                .app_data(app_state.clone())
+                .configure(logs_scope::<mem_db::MemDb>),
+        )
+        .await;
+        let req = test::TestRequest::post()
+            .uri(uri)
+            .append_header((http::header::CONTENT_TYPE, content_type))
+            .append_header((http::header::CONTENT_ENCODING, "gzip"))
+            .set_payload(gzip)
+            .to_request();
+        let res = test::call_service(&app, req).await;
+
+        assert!(res.status().is_success(), "{uri} {content_type}");
+        assert_eq!(
+            app_state.logs.read().unwrap().len(),
+            count,
+            "{uri} {content_type}"
+        );
+    }
+}
+
+#[actix_web::test]
+async fn create_logs_with_idempotency_key() {
+    let csv = "user_agent,response_time\nagent 1,100\nagent 2,200\n";
+    let json = r#"{"user_agent": "agent 1", "response_time": 100}"#;
+
+    for (content_type, body, count) in [("text/csv", csv, 2), ("application/json", json, 1)] {
+        let mem_db = mem_db::MemDb::default();
+        let app_state = web::Data::new(mem_db);
+
+        let app = test::init_service(
+            App::new()
+                .app_data(app_state.clone())
+                .configure(logs_scope::<mem_db::MemDb>),
+        )
+        .await;
+        let post = || {
+            test::TestRequest::post()
+                .uri("/logs")
+                .append_header((http::header::CONTENT_TYPE, content_type))
+                .append_header(("Idempotency-Key", "key-1"))
+                .set_payload(body)
+                .to_request()
+        };
+
+        let res = test::call_service(&app, post()).await;
+        assert!(res.headers().get("Idempotent-Replayed").is_none());
+        let first = test::read_body(res).await;
+
+        let res = test::call_service(&app, post()).await;
+        assert_eq!(res.headers().get("Idempotent-Replayed").unwrap(), "true");
+        assert_eq!(
+            res.headers().get(http::header::CONTENT_TYPE).unwrap(),
+            "application/json",
+            "{content_type}"
+        );
+        let second = test::read_body(res).await;
+
+        assert_eq!(first, second, "{content_type}");
+        assert_eq!(
+            app_state.logs.read().unwrap().len(),
+            count,
+            "{content_type}"
+        );
+    }
+}
+
+#[actix_web::test]
+async fn create_logs_with_bad_idempotency_key() {
+    let mem_db = mem_db::MemDb::default();
+    let app_state = web::Data::new(mem_db);
+
+    let app = test::init_service(
+        App::new()
+            .app_data(app_state.clone())
+            .configure(logs_scope::<mem_db::MemDb>),
+    )
+    .await;
+    let long_key = "k".repeat(256);
+    for key in ["", "a key", long_key.as_str()] {
+        let req = test::TestRequest::post()
+            .uri("/logs")
+            .append_header(("Idempotency-Key", key))
+            .set_json(NewLog {
+                user_agent: "Agent 1".into(),
+                response_time: 100,
+                timestamp: None,
+                id: None,
+            })
+            .to_request();
+        let res = test::call_service(&app, req).await;
+
+        assert_eq!(res.status(), http::StatusCode::BAD_REQUEST, "{key}");
+    }
+    assert!(app_state.logs.read().unwrap().is_empty());
+}
+
+#[actix_web::test]
+async fn retry_failed_request_with_idempotency_key() {
+    let mem_db = mem_db::MemDb::default();
+    let app_state = web::Data::new(mem_db);
+
+    let app = test::init_service(
+        App::new()
+            .app_data(app_state.clone())
+            .configure(logs_scope::<mem_db::MemDb>),
+    )
+    .await;
+    for (body, status) in [
+        (
+            r#"{"user_agent": "agent 1"}"#,
+            http::StatusCode::BAD_REQUEST,
+        ),
+        (
+            r#"{"user_agent": "agent 1", "response_time": 100}"#,
+            http::StatusCode::CREATED,
+        ),
+    ] {
+        let req = test::TestRequest::post()
+            .uri("/logs")
+            .append_header(http::header::ContentType::json())
+            .append_header(("Idempotency-Key", "key-1"))
+            .set_payload(body)
+            .to_request();
+        let res = test::call_service(&app, req).await;
+
+        assert_eq!(res.status(), status);
+    }
+    assert_eq!(app_state.logs.read().unwrap().len(), 1);
+}
+
+#[actix_web::test]
+async fn reuse_idempotency_key_for_another_request() {
+    let mem_db = mem_db::MemDb::default();
+    let app_state = web::Data::new(mem_db);
+
+    let app = test::init_service(
+        App::new()
+            .app_data(app_state.clone())
+            .configure(logs_scope::<mem_db::MemDb>),
+    )
+    .await;
+    let post = |uri: &str, user_agent: &str| {
+        test::TestRequest::post()
+            .uri(uri)
+            .append_header(("Idempotency-Key", "key-1"))
+            .set_json(NewLog {
+                user_agent: user_agent.into(),
+                response_time: 100,
+                timestamp: None,
+                id: None,
+            })
+            .to_request()
+    };
+
+    let res = test::call_service(&app, post("/logs", "Agent 1")).await;
+    assert_eq!(res.status(), http::StatusCode::CREATED);
+
+    // another body, or another query, with the same key
+    for req in [
+        post("/logs", "Agent 2"),
+        post("/logs?mode=strict", "Agent 1"),
+    ] {
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), http::StatusCode::UNPROCESSABLE_ENTITY);
+        let problem: ProblemResponse = test::read_body_json(res).await;
+        assert_eq!(
+            problem.detail.as_deref(),
+            Some("the idempotency key `key-1` was used for another request")
+        );
+    }
+
+    let res = test::call_service(&app, post("/logs", "Agent 1")).await;
+    assert_eq!(res.headers().get("Idempotent-Replayed").unwrap(), "true");
+    assert_eq!(app_state.logs.read().unwrap().len(), 1);
+}
+
+#[actix_web::test]
+async fn create_logs_in_progress_with_idempotency_key() {
+    let mem_db = mem_db::MemDb::default();
+    mem_db
+        .idempotency_keys
+        .write()
+        .unwrap()
+        .insert("key-1".into(), (None, None));
+    let app_state = web::Data::new(mem_db);
+
+    let app = test::init_service(
+        App::new()
+            .app_data(app_state.clone())
+            .configure(logs_scope::<mem_db::MemDb>),
+    )
+    .await;
+    let req = test::TestRequest::post()
+        .uri("/logs")
+        .append_header(("Idempotency-Key", "key-1"))
+        .set_json(NewLog {
+            user_agent: "Agent 1".into(),
+            response_time: 100,
+            timestamp: None,
+            id: None,
+        })
+        .to_request();
+    let res = test::call_service(&app, req).await;
+
+    assert_eq!(res.status(), http::StatusCode::CONFLICT);
+    assert!(app_state.logs.read().unwrap().is_empty());
+}
+
+#[actix_web::test]
+async fn create_logs_with_id() {
+    let mem_db = mem_db::MemDb::default();
+    let app_state = web::Data::new(mem_db);
+
+    let app = test::init_service(
+        App::new()
+            .app_data(app_state.clone())
+            .configure(logs_scope::<mem_db::MemDb>),
+    )
+    .await;
+    let id = Uuid::new_v4();
+    // the same log is stored once, and another log with its id is refused
+    for (response_time, status) in [
+        (100, http::StatusCode::CREATED),
+        (100, http::StatusCode::OK),
+        (200, http::StatusCode::CONFLICT),
+    ] {
+        let req = test::TestRequest::post()
+            .uri("/logs")
+            .set_json(NewLog {
+                user_agent: "Agent 1".into(),
+                response_time,
+                timestamp: None,
+                id: Some(id),
+            })
+            .to_request();
+        let res = test::call_service(&app, req).await;
+
+        assert_eq!(res.status(), status, "{response_time}");
+        if status.is_success() {
+            let res: LogResponse = test::read_body_json(res).await;
+            assert_eq!((res.id, res.response_time), (id, 100));
+        }
+    }
+    assert_eq!(app_state.logs.read().unwrap().len(), 1);
+}
+
+#[actix_web::test]
+async fn create_bulk_logs_with_duplicates() {
+    let id1 = Uuid::new_v4();
+    let id2 = Uuid::new_v4();
+    let ndjson = format!(
+        "{{\"user_agent\":\"agent 1\",\"response_time\":100,\"id\":\"{id1}\"}}\n\
+        {{\"user_agent\":\"agent 2\",\"response_time\":200,\"id\":\"{id2}\"}}\n\
+        {{\"user_agent\":\"agent 1\",\"response_time\":100,\"id\":\"{id1}\"}}\n"
+    );
+    let csv = format!("id,user_agent,response_time\n{id2},agent 2,200\n,agent 3,300\n");
+
+    let mem_db = mem_db::MemDb::default();
+    let app_state = web::Data::new(mem_db);
+
+    let app = test::init_service(
+        App::new()
+            .app_data(app_state.clone())
+            .configure(logs_scope::<mem_db::MemDb>),
+    )
+    .await;
+    for (content_type, body, expected) in [
+        ("application/x-ndjson", ndjson, (2, 1)),
+        ("text/csv", csv, (1, 1)),
+    ] {
+        let req = test::TestRequest::post()
+            .uri("/logs")
+            .append_header((http::header::CONTENT_TYPE, content_type))
+            .set_payload(body)
+            .to_request();
+        let res: LoadResponse = test::call_and_read_body_json(&app, req).await;
+
+        assert_eq!((res.accepted, res.duplicates), expected, "{content_type}");
+    }
+    assert_eq!(app_state.logs.read().unwrap().len(), 3);
 }
 
 #[actix_web::test]
@@ -46,7 +497,7 @@ async fn get_logs() {
         id: Uuid::new_v4(),
         user_agent: "agent 1".into(),
         response_time: 100,
-        timestamp: Utc::now().trunc_subsecs(0),
+        timestamp: Utc::now().trunc_subsecs(0) - Duration::seconds(1),
     };
     let log2 = Log {
         id: Uuid::new_v4(),
@@ -67,7 +518,282 @@ async fn get_logs() {
     let req = actix_web::test::TestRequest::get()
         .uri("/logs")
         .to_request();
-    let res: Vec<LogResponse> = test::call_and_read_body_json(&app, req).await;
+    let res: LogsResponse = test::call_and_read_body_json(&app, req).await;
+
+    assert_eq!(
+        res,
+        LogsResponse {
+            logs: vec![LogResponse::from(log1), LogResponse::from(log2)],
+            next_cursor: None,
+        }
+    );
+}
+
+#[actix_web::test]
+async fn get_logs_by_accept() {
+    let log1 = Log {
+        id: Uuid::new_v4(),
+        user_agent: "agent 1".into(),
+        response_time: 100,
+        timestamp: "2023-01-02T03:04:05Z".parse().unwrap(),
+    };
+    let log2 = Log {
+        id: Uuid::new_v4(),
+        user_agent: "agent 2".into(),
+        response_time: 200,
+        timestamp: "2023-02-03T04:05:06Z".parse().unwrap(),
+    };
+
+    let mem_db = mem_db::MemDb::from(vec![log1.clone(), log2.clone()]);
+    let app_state = web::Data::new(mem_db);
+
+    let app = test::init_service(
+        App::new()
+            .app_data(app_state.clone())
+            .configure(logs_scope::<mem_db::MemDb>),
+    )
+    .await;
+
+    let req = test::TestRequest::get()
+        .uri("/logs")
+        .append_header((http::header::ACCEPT, "text/csv"))
+        .to_request();
+    let res_body = test::call_and_read_body(&app, req).await;
+
+    assert_eq!(
+        String::from_utf8(res_body.to_vec()).unwrap(),
+        format!(
+            "agent 1,100,2023-01-02T03:04:05Z,{}\n\
+            agent 2,200,2023-02-03T04:05:06Z,{}\n",
+            log1.id, log2.id
+        )
+    );
+
+    let req = test::TestRequest::get()
+        .uri("/logs")
+        .append_header((
+            http::header::ACCEPT,
+            "application/x-ndjson, application/json;q=0.5",
+        ))
+        .to_request();
+    let res_body = test::call_and_read_body(&app, req).await;
+    let logs = String::from_utf8(res_body.to_vec())
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str::<LogResponse>(line).unwrap())
+        .collect::<Vec<_>>();
+
+    assert_eq!(logs, vec![LogResponse::from(log1), LogResponse::from(log2)]);
+
+    let req = test::TestRequest::get()
+        .uri("/logs")
+        .append_header((http::header::ACCEPT, "application/xml"))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+
+    assert_eq!(res.status(), http::StatusCode::NOT_ACCEPTABLE);
+}
+
+#[actix_web::test]
+async fn get_log() {
+    let log = Log {
+        id: Uuid::new_v4(),
+        user_agent: "agent 1".into(),
+        response_time: 100,
+        timestamp: Utc::now().trunc_subsecs(0),
+    };
+
+    let mem_db = mem_db::MemDb::from(vec![log.clone()]);
+    let app_state = web::Data::new(mem_db);
+
+    let app = test::init_service(
+        App::new()
+            .app_data(app_state.clone())
+            .configure(logs_scope::<mem_db::MemDb>),
+    )
+    .await;
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/logs/{}", log.id))
+        .to_request();
+    let res: LogResponse = test::call_and_read_body_json(&app, req).await;
+
+    assert_eq!(res, LogResponse::from(log));
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/logs/{}", Uuid::new_v4()))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+
+    assert_eq!(res.status(), http::StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn delete_log() {
+    let log = Log {
+        id: Uuid::new_v4(),
+        user_agent: "agent 1".into(),
+        response_time: 100,
+        timestamp: Utc::now().trunc_subsecs(0),
+    };
+
+    let mem_db = mem_db::MemDb::from(vec![log.clone()]);
+    let app_state = web::Data::new(mem_db);
+
+    let app = test::init_service(
+        App::new()
+            .app_data(app_state.clone())
+            .configure(logs_scope::<mem_db::MemDb>),
+    )
+    .await;
+
+    for status in [http::StatusCode::NO_CONTENT, http::StatusCode::NOT_FOUND] {
+        let req = test::TestRequest::delete()
+            .uri(&format!("/logs/{}", log.id))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+
+        assert_eq!(res.status(), status);
+    }
+    assert!(app_state.logs.read().unwrap().is_empty());
+}
+
+#[actix_web::test]
+async fn delete_logs_in_range() {
+    let logs = (1..=4)
+        .map(|day| Log {
+            id: Uuid::new_v4(),
+            user_agent: format!("agent {day}"),
+            response_time: 100,
+            timestamp: format!("2023-01-0{day}T00:00:00Z").parse().unwrap(),
+        })
+        .collect::<Vec<_>>();
+
+    let mem_db = mem_db::MemDb::from(logs);
+    let app_state = web::Data::new(mem_db);
+
+    let app = test::init_service(
+        App::new()
+            .app_data(app_state.clone())
+            .configure(logs_scope::<mem_db::MemDb>),
+    )
+    .await;
+
+    let req = test::TestRequest::delete()
+        .uri("/logs?from=2023-01-02T00:00:00Z&until=2023-01-03T00:00:00Z")
+        .to_request();
+    let res: DeleteResponse = test::call_and_read_body_json(&app, req).await;
+
+    assert_eq!(res, DeleteResponse { deleted: 2 });
+    let user_agents = app_state
+        .logs
+        .read()
+        .unwrap()
+        .iter()
+        .map(|log| log.user_agent.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(user_agents, vec!["agent 1", "agent 4"]);
+
+    // without a bound, nothing is deleted
+    let req = test::TestRequest::delete().uri("/logs").to_request();
+    let res = test::call_service(&app, req).await;
+
+    assert_eq!(res.status(), http::StatusCode::BAD_REQUEST);
+    assert_eq!(app_state.logs.read().unwrap().len(), 2);
+}
+
+#[actix_web::test]
+async fn get_logs_by_page() {
+    let logs = (0..5)
+        .map(|i| Log {
+            id: Uuid::new_v4(),
+            user_agent: format!("agent {i}"),
+            response_time: 100 * i,
+            timestamp: Utc::now().trunc_subsecs(0) + Duration::seconds(i.into()),
+        })
+        .collect::<Vec<_>>();
+
+    let mem_db = mem_db::MemDb::from(logs.clone());
+    let app_state = web::Data::new(mem_db);
+
+    let app = test::init_service(
+        App::new()
+            .app_data(app_state.clone())
+            .configure(logs_scope::<mem_db::MemDb>),
+    )
+    .await;
+
+    let mut pages = Vec::new();
+    let mut cursor = None;
+    loop {
+        let uri = match cursor {
+            Some(cursor) => format!("/logs?limit=2&cursor={cursor}"),
+            None => "/logs?limit=2".to_string(),
+        };
+        let req = test::TestRequest::get().uri(&uri).to_request();
+        let res: LogsResponse = test::call_and_read_body_json(&app, req).await;
+
+        pages.push(res.logs);
+        cursor = res.next_cursor;
+        if cursor.is_none() {
+            break;
+        }
+    }
+
+    let expected = logs
+        .chunks(2)
+        .map(|chunk| chunk.iter().cloned().map(LogResponse::from).collect())
+        .collect::<Vec<Vec<_>>>();
+    assert_eq!(pages, expected);
+}
+
+#[actix_web::test]
+async fn cursor_round_trip() {
+    for timestamp in [
+        "2023-06-01T00:00:00.123456Z",
+        "1970-01-01T00:00:00Z",
+        "1969-12-31T23:59:59.999999Z",
+        "1900-01-01T00:00:00Z",
+    ] {
+        let cursor = Cursor {
+            timestamp: timestamp.parse().unwrap(),
+            id: Uuid::new_v4(),
+        };
+
+        assert_eq!(
+            cursor.to_string().parse::<Cursor>(),
+            Ok(cursor),
+            "{timestamp}"
+        );
+    }
+}
+
+#[actix_web::test]
+async fn get_logs_with_filter() {
+    let logs = [("curl/7.88", 120), ("curl/8.1", 640), ("Mozilla/5.0", 900)]
+        .into_iter()
+        .enumerate()
+        .map(|(i, (user_agent, response_time))| Log {
+            id: Uuid::new_v4(),
+            user_agent: user_agent.into(),
+            response_time,
+            timestamp: Utc::now().trunc_subsecs(0) + Duration::seconds(i as i64),
+        })
+        .collect::<Vec<_>>();
+
+    let mem_db = mem_db::MemDb::from(logs.clone());
+    let app_state = web::Data::new(mem_db);
+
+    let app = test::init_service(
+        App::new()
+            .app_data(app_state.clone())
+            .configure(logs_scope::<mem_db::MemDb>),
+    )
+    .await;
+    let req = test::TestRequest::get()
+        .uri("/logs?user_agent=curl&user_agent_match=prefix&min_response_time=500")
+        .to_request();
+    let res: LogsResponse = test::call_and_read_body_json(&app, req).await;
 
-    assert_eq!(res, vec![LogResponse::from(log1), LogResponse::from(log2)]);
+    assert_eq!(res.logs, vec![LogResponse::from(logs[1].clone())]);
 }
@@ -1,25 +1,92 @@
-use std::fs;
-use std::io;
-use std::path;
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::collections::HashSet;
 use std::sync::RwLock;
 
 use async_trait::async_trait;
 use chrono::DateTime;
 use chrono::SubsecRound;
+use chrono::TimeZone;
 use chrono::Utc;
-use error_stack::IntoReport;
-use error_stack::ResultExt;
+use error_stack::Report;
+use futures_util::future;
+use futures_util::stream;
+use futures_util::stream::BoxStream;
+use futures_util::Stream;
+use futures_util::StreamExt;
+use itertools::Itertools;
 use uuid::Uuid;
 
+use server::db::batch::LogBatch;
+use server::db::partitions::next_month;
+use server::db::partitions::partition_month;
+use server::db::partitions::partition_months;
+use server::db::partitions::partition_name;
 use server::db::DbTrait;
 use server::errors::AppError;
+use server::models::health::PoolStatus;
+use server::models::idempotency::IdempotencyClaim;
+use server::models::idempotency::StoredResponse;
+use server::models::load::LoadReport;
+use server::models::load::ReadError;
+use server::models::logs::InsertedLog;
 use server::models::logs::Log;
+use server::models::stats::BucketStats;
+use server::models::stats::ResponseTimeStats;
+use server::states::DbState;
 
+use api::params::Bucket;
+use api::params::Cursor;
+use api::params::ImportMode;
+use api::params::LoadParams;
+use api::params::LogFilter;
+use api::params::StatsGroupBy;
 use api::requests::logs::NewLog;
 
-#[derive(Debug, Default)]
+/// hash of the request that claimed a key, and its response, `None` while it is in progress.
+/// a key without a hash is replayed to any request, like the rows stored before the hashes
+pub type IdempotencyKey = (Option<Vec<u8>>, Option<StoredResponse>);
+
+#[derive(Debug)]
 pub struct MemDb {
     pub logs: RwLock<Vec<Log>>,
+    pub batch_size: usize,
+    /// every query fails like the database is down
+    pub unavailable: bool,
+    /// connections of the pool, set to `idle: 0` to simulate a saturated pool
+    pub pool_status: PoolStatus,
+    pub migration_version: Option<i64>,
+    pub idempotency_keys: RwLock<HashMap<String, IdempotencyKey>>,
+    /// names of the monthly partitions
+    pub partitions: RwLock<BTreeSet<String>>,
+    /// the `size_hint` of every load
+    pub size_hints: RwLock<Vec<Option<u64>>>,
+}
+impl Default for MemDb {
+    fn default() -> Self {
+        Self {
+            logs: RwLock::default(),
+            batch_size: DbState::DEFAULT_BATCH_SIZE,
+            unavailable: false,
+            pool_status: PoolStatus {
+                size: 1,
+                idle: 1,
+                max_connections: 10,
+            },
+            migration_version: Some(20230604003127),
+            idempotency_keys: RwLock::default(),
+            partitions: RwLock::default(),
+            size_hints: RwLock::default(),
+        }
+    }
+}
+impl MemDb {
+    fn check_available(&self) -> error_stack::Result<(), AppError> {
+        if self.unavailable {
+            return Err(Report::new(sqlx::Error::PoolTimedOut).change_context(AppError));
+        }
+        Ok(())
+    }
 }
 impl From<Vec<Log>> for MemDb {
     fn from(logs: Vec<Log>) -> Self {
@@ -41,10 +108,16 @@ impl DbTrait for MemDb {
         user_agent: &str,
         response_time: i32,
         timestamp: Option<DateTime<Utc>>,
-    ) -> error_stack::Result<Log, AppError> {
+        id: Option<Uuid>,
+    ) -> error_stack::Result<InsertedLog, AppError> {
+        self.check_available()?;
         let mut logs = self.logs.write().unwrap();
+        if let Some(log) = id.and_then(|id| logs.iter().find(|log| log.id == id)) {
+            return Ok(InsertedLog::Existing(log.clone()));
+        }
+
         let log = Log {
-            id: Uuid::new_v4(),
+            id: id.unwrap_or_else(Uuid::new_v4),
             user_agent: user_agent.into(),
             response_time,
             timestamp: timestamp.unwrap_or_else(|| Utc::now().trunc_subsecs(0)),
@@ -52,57 +125,339 @@ impl DbTrait for MemDb {
 
         logs.push(log.clone());
 
-        Ok(log)
+        Ok(InsertedLog::New(log))
+    }
+
+    async fn get_log(&self, id: Uuid) -> error_stack::Result<Option<Log>, AppError> {
+        self.check_available()?;
+        let logs = self.logs.read().unwrap();
+
+        Ok(logs.iter().find(|log| log.id == id).cloned())
     }
 
     async fn get_logs(
         &self,
         from: Option<DateTime<Utc>>,
         until: Option<DateTime<Utc>>,
+        filter: &LogFilter,
+        cursor: Option<Cursor>,
+        limit: i64,
     ) -> error_stack::Result<Vec<Log>, AppError> {
+        self.check_available()?;
         let logs = self.logs.read().unwrap();
 
-        let logs = logs
+        let mut logs = logs
             .iter()
             .filter(|log| {
                 from.map(|from| log.timestamp >= from).unwrap_or(true)
                     && until.map(|until| log.timestamp <= until).unwrap_or(true)
+                    && filter.matches(&log.user_agent, log.response_time)
+                    && cursor.map(|cursor| log.cursor() > cursor).unwrap_or(true)
             })
             .cloned()
             .collect::<Vec<_>>();
+        logs.sort_by_key(Log::cursor);
+        logs.truncate(limit as usize);
 
         Ok(logs)
     }
 
-    async fn load_file<P>(&self, file_path: P) -> error_stack::Result<u64, AppError>
+    fn stream_logs(
+        &self,
+        from: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+        filter: &LogFilter,
+    ) -> BoxStream<'static, error_stack::Result<Log, AppError>> {
+        if let Err(e) = self.check_available() {
+            return stream::once(future::ready(Err(e))).boxed();
+        }
+        let logs = self.logs.read().unwrap();
+
+        let logs = logs
+            .iter()
+            .filter(|log| {
+                from.map(|from| log.timestamp >= from).unwrap_or(true)
+                    && until.map(|until| log.timestamp <= until).unwrap_or(true)
+                    && filter.matches(&log.user_agent, log.response_time)
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+
+        stream::iter(logs).map(Ok).boxed()
+    }
+
+    async fn delete_log(&self, id: Uuid) -> error_stack::Result<bool, AppError> {
+        self.check_available()?;
+        let mut logs = self.logs.write().unwrap();
+        let len = logs.len();
+        logs.retain(|log| log.id != id);
+
+        Ok(logs.len() < len)
+    }
+
+    async fn delete_logs(
+        &self,
+        from: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+    ) -> error_stack::Result<u64, AppError> {
+        self.check_available()?;
+        let mut logs = self.logs.write().unwrap();
+        let len = logs.len();
+        logs.retain(|log| {
+            from.map(|from| log.timestamp < from).unwrap_or(false)
+                || until.map(|until| log.timestamp > until).unwrap_or(false)
+        });
+
+        Ok((len - logs.len()) as u64)
+    }
+
+    async fn purge_logs(
+        &self,
+        before: DateTime<Utc>,
+        limit: i64,
+    ) -> error_stack::Result<u64, AppError> {
+        self.check_available()?;
+        let mut logs = self.logs.write().unwrap();
+        let mut purged = 0;
+        logs.retain(|log| {
+            let purge = log.timestamp < before && purged < limit;
+            if purge {
+                purged += 1;
+            }
+            !purge
+        });
+
+        Ok(purged as u64)
+    }
+
+    // only the names of the partitions are kept, `purge_logs` deletes all of the expired logs
+    async fn create_partitions(
+        &self,
+        from: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> error_stack::Result<Vec<String>, AppError> {
+        self.check_available()?;
+        let mut partitions = self.partitions.write().unwrap();
+        let created = partition_months(from, until)
+            .into_iter()
+            .map(partition_name)
+            .filter(|name| partitions.insert(name.clone()))
+            .collect();
+
+        Ok(created)
+    }
+
+    async fn drop_partitions(
+        &self,
+        before: DateTime<Utc>,
+    ) -> error_stack::Result<Vec<String>, AppError> {
+        self.check_available()?;
+        let mut dropped = Vec::new();
+        self.partitions.write().unwrap().retain(|name| {
+            let expired = partition_month(name)
+                .and_then(next_month)
+                .is_some_and(|until| until <= before);
+            if expired {
+                dropped.push(name.clone());
+            }
+            !expired
+        });
+
+        Ok(dropped)
+    }
+
+    async fn claim_idempotency_key(
+        &self,
+        key: &str,
+        request_hash: &[u8],
+    ) -> error_stack::Result<IdempotencyClaim, AppError> {
+        self.check_available()?;
+        let mut keys = self.idempotency_keys.write().unwrap();
+        let claim = match keys.get(key) {
+            Some((Some(stored_hash), _)) if stored_hash != request_hash => {
+                IdempotencyClaim::Mismatch
+            }
+            Some((_, Some(response))) => IdempotencyClaim::Completed(response.clone()),
+            Some((_, None)) => IdempotencyClaim::InProgress,
+            None => {
+                keys.insert(key.to_string(), (Some(request_hash.to_vec()), None));
+                IdempotencyClaim::Claimed
+            }
+        };
+
+        Ok(claim)
+    }
+
+    async fn complete_idempotency_key(
+        &self,
+        key: &str,
+        response: &StoredResponse,
+    ) -> error_stack::Result<(), AppError> {
+        self.check_available()?;
+        let mut keys = self.idempotency_keys.write().unwrap();
+        if let Some((_, stored)) = keys.get_mut(key) {
+            *stored = Some(response.clone());
+        }
+
+        Ok(())
+    }
+
+    async fn release_idempotency_key(&self, key: &str) -> error_stack::Result<(), AppError> {
+        self.check_available()?;
+        let mut keys = self.idempotency_keys.write().unwrap();
+        if let Some((_, None)) = keys.get(key) {
+            keys.remove(key);
+        }
+
+        Ok(())
+    }
+
+    async fn get_stats(
+        &self,
+        from: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+        group_by: Option<StatsGroupBy>,
+    ) -> error_stack::Result<Vec<ResponseTimeStats>, AppError> {
+        self.check_available()?;
+        let logs = self.logs.read().unwrap();
+
+        let logs = logs.iter().filter(|log| {
+            from.map(|from| log.timestamp >= from).unwrap_or(true)
+                && until.map(|until| log.timestamp <= until).unwrap_or(true)
+        });
+
+        let stats = match group_by {
+            None => {
+                let response_times = logs.map(|log| log.response_time).collect();
+                vec![ResponseTimeStats::from_response_times(None, response_times)]
+            }
+            Some(StatsGroupBy::UserAgent) => logs
+                .map(|log| (log.user_agent.clone(), log.response_time))
+                .into_group_map()
+                .into_iter()
+                .sorted_by(|(a, _), (b, _)| a.cmp(b))
+                .map(|(user_agent, response_times)| {
+                    ResponseTimeStats::from_response_times(Some(user_agent), response_times)
+                })
+                .collect(),
+        };
+
+        Ok(stats)
+    }
+
+    async fn get_timeseries(
+        &self,
+        from: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+        bucket: Bucket,
+    ) -> error_stack::Result<Vec<BucketStats>, AppError> {
+        self.check_available()?;
+        let logs = self.logs.read().unwrap();
+
+        let stats = logs
+            .iter()
+            .filter(|log| {
+                from.map(|from| log.timestamp >= from).unwrap_or(true)
+                    && until.map(|until| log.timestamp <= until).unwrap_or(true)
+            })
+            .map(|log| {
+                let seconds = log.timestamp.timestamp();
+                let bucket_start = seconds - seconds.rem_euclid(bucket.seconds());
+                (bucket_start, log.response_time)
+            })
+            .into_group_map()
+            .into_iter()
+            .sorted_by_key(|(bucket_start, _)| *bucket_start)
+            .map(|(bucket_start, response_times)| {
+                let bucket_start = Utc.timestamp_opt(bucket_start, 0).unwrap();
+                BucketStats::from_response_times(bucket_start, response_times)
+            })
+            .collect();
+
+        Ok(stats)
+    }
+
+    async fn ping(&self) -> error_stack::Result<(), AppError> {
+        self.check_available()
+    }
+
+    fn pool_status(&self) -> PoolStatus {
+        self.pool_status
+    }
+
+    async fn migration_version(&self) -> error_stack::Result<Option<i64>, AppError> {
+        self.check_available()?;
+        Ok(self.migration_version)
+    }
+
+    async fn load_logs<S>(
+        &self,
+        new_logs: S,
+        params: &LoadParams,
+        size_hint: Option<u64>,
+    ) -> error_stack::Result<LoadReport, AppError>
     where
-        P: AsRef<path::Path> + Send,
+        S: Stream<Item = Result<NewLog, ReadError>> + Send,
     {
-        let file = fs::File::open(file_path)
-            .into_report()
-            .change_context(AppError)?;
-        let reader = io::BufReader::new(file);
-        let new_logs = csv::ReaderBuilder::new()
-            .has_headers(false)
-            .from_reader(reader)
-            .into_deserialize::<NewLog>();
-
-        let mut count = 0;
-        let mut logs = self.logs.write().unwrap();
-        for new_log in new_logs {
-            let new_log = new_log.into_report().change_context(AppError)?;
-            let log = Log {
-                id: Uuid::new_v4(),
-                user_agent: new_log.user_agent,
-                response_time: new_log.response_time,
-                timestamp: new_log
-                    .timestamp
-                    .unwrap_or_else(|| Utc::now().trunc_subsecs(0)),
+        self.check_available()?;
+        self.size_hints.write().unwrap().push(size_hint);
+        let mode = params.mode;
+        let mut report = LoadReport::new(params.max_errors());
+        let mut batch = LogBatch::new(self.batch_size);
+        let mut loaded = Vec::new();
+        let mut ids = self
+            .logs
+            .read()
+            .unwrap()
+            .iter()
+            .map(|log| log.id)
+            .collect::<HashSet<_>>();
+        futures_util::pin_mut!(new_logs);
+        while let Some(new_log) = new_logs.next().await {
+            let new_log = match new_log {
+                Ok(new_log) => new_log,
+                Err(ReadError::Row(e)) => {
+                    report.reject(e);
+                    if mode.stops_on_error() {
+                        break;
+                    }
+                    continue;
+                }
+                Err(e) => return Err(Report::new(e).change_context(AppError)),
             };
-            logs.push(log);
-            count += 1;
+            if batch.push(new_log) {
+                take_new_logs(&batch, &mut ids, &mut loaded, &mut report);
+                batch.clear();
+            }
+        }
+        if !batch.is_empty() {
+            take_new_logs(&batch, &mut ids, &mut loaded, &mut report);
+        }
+
+        if mode == ImportMode::Atomic && report.rejected > 0 {
+            report.accepted = 0;
+            report.duplicates = 0;
+        } else {
+            self.logs.write().unwrap().extend(loaded);
         }
 
-        Ok(count)
+        Ok(report)
+    }
+}
+
+// logs with the id of a stored or already loaded log are duplicates
+fn take_new_logs(
+    batch: &LogBatch,
+    ids: &mut HashSet<Uuid>,
+    loaded: &mut Vec<Log>,
+    report: &mut LoadReport,
+) {
+    for log in batch.logs() {
+        if ids.insert(log.id) {
+            loaded.push(log);
+            report.accepted += 1;
+        } else {
+            report.duplicates += 1;
+        }
     }
 }
@@ -0,0 +1,129 @@
+use chrono::DateTime;
+use chrono::Utc;
+use pretty_assertions::assert_eq;
+use uuid::Uuid;
+
+use server::db::partitions::partition_month;
+use server::db::partitions::partition_months;
+use server::db::partitions::partition_name;
+use server::db::DbTrait;
+use server::partitions::PartitionPolicy;
+use server::states::DbState;
+
+mod mem_db;
+mod pg_db;
+
+fn utc(timestamp: &str) -> DateTime<Utc> {
+    timestamp.parse().unwrap()
+}
+
+#[test]
+fn monthly_partitions() {
+    let months = partition_months(utc("2023-11-15T12:00:00Z"), utc("2024-02-01T00:00:00Z"));
+    let names = months
+        .iter()
+        .copied()
+        .map(partition_name)
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        names,
+        vec![
+            "logs_y2023m11",
+            "logs_y2023m12",
+            "logs_y2024m01",
+            "logs_y2024m02"
+        ]
+    );
+    assert_eq!(months[0], utc("2023-11-01T00:00:00Z"));
+
+    for month in months {
+        assert_eq!(partition_month(&partition_name(month)), Some(month));
+    }
+    for name in ["logs_default", "logs_y2023m13", "logs_y23m01", "logs"] {
+        assert_eq!(partition_month(name), None, "{name}");
+    }
+}
+
+#[actix_web::test]
+async fn create_partitions_ahead() {
+    let mem_db = mem_db::MemDb::default();
+
+    let created = PartitionPolicy::default()
+        .with_months_ahead(1)
+        .create(&mem_db, utc("2023-12-31T23:59:59Z"))
+        .await
+        .unwrap();
+
+    assert_eq!(created, vec!["logs_y2023m12", "logs_y2024m01"]);
+
+    // a month later, only the last month is missing
+    let created = PartitionPolicy::default()
+        .with_months_ahead(1)
+        .create(&mem_db, utc("2024-01-15T00:00:00Z"))
+        .await
+        .unwrap();
+
+    assert_eq!(created, vec!["logs_y2024m02"]);
+}
+
+#[actix_web::test]
+async fn drop_expired_partitions() {
+    let mem_db = mem_db::MemDb::default();
+    mem_db
+        .create_partitions(utc("2023-11-15T00:00:00Z"), utc("2024-01-15T00:00:00Z"))
+        .await
+        .unwrap();
+
+    // december still has logs that are not expired
+    let dropped = mem_db
+        .drop_partitions(utc("2023-12-15T00:00:00Z"))
+        .await
+        .unwrap();
+
+    assert_eq!(dropped, vec!["logs_y2023m11"]);
+    assert_eq!(
+        mem_db.partitions.read().unwrap().iter().collect::<Vec<_>>(),
+        vec!["logs_y2023m12", "logs_y2024m01"]
+    );
+}
+
+// logs of months without a partition wait in `logs_default`, and are moved to the partition
+// when it is created
+#[actix_web::test]
+async fn create_and_drop_partitions_in_postgres() {
+    let db_state = pg_db::connect(1).await;
+
+    // a month that the server doesn't create a partition for
+    let month = utc("1983-03-01T00:00:00Z");
+    let log = db_state
+        .insert_log("agent", 100, Some(utc("1983-03-15T12:00:00Z")), None)
+        .await
+        .unwrap()
+        .into_log();
+    assert_eq!(count_rows(&db_state, "logs_default", log.id).await, 1);
+
+    let created = db_state.create_partitions(month, month).await.unwrap();
+    assert_eq!(created, vec!["logs_y1983m03"]);
+    assert_eq!(count_rows(&db_state, "logs_default", log.id).await, 0);
+    assert_eq!(count_rows(&db_state, "logs_y1983m03", log.id).await, 1);
+    assert_eq!(db_state.get_log(log.id).await.unwrap(), Some(log.clone()));
+
+    let created = db_state.create_partitions(month, month).await.unwrap();
+    assert!(created.is_empty());
+
+    let dropped = db_state
+        .drop_partitions(utc("1983-04-01T00:00:00Z"))
+        .await
+        .unwrap();
+    assert_eq!(dropped, vec!["logs_y1983m03"]);
+    assert_eq!(db_state.get_log(log.id).await.unwrap(), None);
+}
+
+async fn count_rows(db_state: &DbState, table: &str, id: Uuid) -> i64 {
+    sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {table} WHERE id = $1"))
+        .bind(id)
+        .fetch_one(&**db_state)
+        .await
+        .unwrap()
+}
@@ -0,0 +1,14 @@
+use std::env;
+
+use sqlx::postgres::PgPoolOptions;
+
+use server::states::DbState;
+
+// tests against postgres need a migrated database, like building the server does
+pub async fn connect(max_connections: u32) -> DbState {
+    dotenv::dotenv().ok();
+    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL is required by the tests");
+    DbState::connect(&database_url, max_connections, PgPoolOptions::new())
+        .await
+        .unwrap()
+}
@@ -44,6 +44,7 @@ async fn ping_post_logs() {
             user_agent: "Agent 1".into(),
             response_time: 100,
             timestamp: None,
+            id: None,
         })
         .to_request();
     let res = test::call_service(&app, req).await;
@@ -83,5 +84,6 @@ async fn ping_post_csv() {
         .to_request();
     let res = test::call_service(&app, req).await;
 
-    assert_eq!(res.status(), http::StatusCode::OK);
+    // csv files are only taken from multipart bodies
+    assert_eq!(res.status(), http::StatusCode::BAD_REQUEST);
 }
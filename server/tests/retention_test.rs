@@ -0,0 +1,43 @@
+use std::num::NonZeroU32;
+
+use chrono::Duration;
+use chrono::Utc;
+use pretty_assertions::assert_eq;
+use uuid::Uuid;
+
+use server::models::logs::Log;
+use server::retention::RetentionPolicy;
+
+mod mem_db;
+
+#[actix_web::test]
+async fn purge_expired_logs() {
+    let now = Utc::now();
+    let logs = (0..7)
+        .map(|days| Log {
+            id: Uuid::new_v4(),
+            user_agent: format!("agent {days}"),
+            response_time: 100,
+            timestamp: now - Duration::days(days),
+        })
+        .collect::<Vec<_>>();
+
+    let mem_db = mem_db::MemDb::from(logs);
+
+    // logs older than 2 days are purged 2 at a time
+    let retention =
+        RetentionPolicy::new(Duration::days(2)).with_batch_size(NonZeroU32::new(2).unwrap());
+    let purged = retention.purge(&mem_db, now).await.unwrap();
+
+    assert_eq!(purged, 4);
+    let user_agents = mem_db
+        .logs
+        .read()
+        .unwrap()
+        .iter()
+        .map(|log| log.user_agent.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(user_agents, vec!["agent 0", "agent 1", "agent 2"]);
+
+    assert_eq!(retention.purge(&mem_db, now).await.unwrap(), 0);
+}
@@ -0,0 +1,134 @@
+use actix_web::test;
+use actix_web::web;
+use actix_web::App;
+use chrono::SubsecRound;
+use chrono::Utc;
+use pretty_assertions::assert_eq;
+use uuid::Uuid;
+
+use server::models::logs::Log;
+use server::scopes::stats::stats_scope;
+
+use api::responses::stats::BucketStatsResponse;
+use api::responses::stats::StatsResponse;
+
+mod mem_db;
+
+fn log(user_agent: &str, response_time: i32) -> Log {
+    Log {
+        id: Uuid::new_v4(),
+        user_agent: user_agent.into(),
+        response_time,
+        timestamp: Utc::now().trunc_subsecs(0),
+    }
+}
+
+fn log_at(timestamp: &str, response_time: i32) -> Log {
+    Log {
+        timestamp: timestamp.parse().unwrap(),
+        ..log("agent", response_time)
+    }
+}
+
+#[actix_web::test]
+async fn get_stats() {
+    let logs = (1..=10).map(|i| log("agent", i * 100)).collect::<Vec<_>>();
+
+    let mem_db = mem_db::MemDb::from(logs);
+    let app_state = web::Data::new(mem_db);
+
+    let app = test::init_service(
+        App::new()
+            .app_data(app_state.clone())
+            .configure(stats_scope::<mem_db::MemDb>),
+    )
+    .await;
+    let req = test::TestRequest::get().uri("/stats").to_request();
+    let res: Vec<StatsResponse> = test::call_and_read_body_json(&app, req).await;
+
+    assert_eq!(
+        res,
+        vec![StatsResponse {
+            user_agent: None,
+            count: 10,
+            min: Some(100),
+            max: Some(1000),
+            mean: Some(550.0),
+            p50: Some(550.0),
+            p95: Some(955.0),
+            p99: Some(991.0),
+        }]
+    );
+}
+
+#[actix_web::test]
+async fn get_stats_by_user_agent() {
+    let logs = vec![
+        log("agent b", 300),
+        log("agent a", 100),
+        log("agent b", 500),
+    ];
+
+    let mem_db = mem_db::MemDb::from(logs);
+    let app_state = web::Data::new(mem_db);
+
+    let app = test::init_service(
+        App::new()
+            .app_data(app_state.clone())
+            .configure(stats_scope::<mem_db::MemDb>),
+    )
+    .await;
+    let req = test::TestRequest::get()
+        .uri("/stats?group_by=user_agent")
+        .to_request();
+    let res: Vec<StatsResponse> = test::call_and_read_body_json(&app, req).await;
+
+    let counts = res
+        .iter()
+        .map(|stats| (stats.user_agent.as_deref(), stats.count, stats.p50))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        counts,
+        vec![
+            (Some("agent a"), 1, Some(100.0)),
+            (Some("agent b"), 2, Some(400.0)),
+        ]
+    );
+}
+
+#[actix_web::test]
+async fn get_timeseries() {
+    let logs = vec![
+        log_at("2023-06-01T10:00:10Z", 100),
+        log_at("2023-06-01T10:04:59Z", 300),
+        log_at("2023-06-01T10:05:00Z", 200),
+        log_at("2023-06-01T10:31:00Z", 400),
+    ];
+
+    let mem_db = mem_db::MemDb::from(logs);
+    let app_state = web::Data::new(mem_db);
+
+    let app = test::init_service(
+        App::new()
+            .app_data(app_state.clone())
+            .configure(stats_scope::<mem_db::MemDb>),
+    )
+    .await;
+    let req = test::TestRequest::get()
+        .uri("/stats/timeseries?bucket=5m")
+        .to_request();
+    let res: Vec<BucketStatsResponse> = test::call_and_read_body_json(&app, req).await;
+
+    let buckets = res
+        .iter()
+        .map(|stats| (stats.bucket.to_rfc3339(), stats.count, stats.mean))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        buckets,
+        vec![
+            ("2023-06-01T10:00:00+00:00".to_string(), 2, Some(200.0)),
+            ("2023-06-01T10:05:00+00:00".to_string(), 1, Some(200.0)),
+            ("2023-06-01T10:30:00+00:00".to_string(), 1, Some(400.0)),
+        ]
+    );
+}
@@ -0,0 +1,206 @@
+use actix_web::http;
+use actix_web::test;
+use actix_web::web;
+use actix_web::App;
+use chrono::DateTime;
+use chrono::Duration;
+use chrono::TimeZone;
+use chrono::Utc;
+use pretty_assertions::assert_eq;
+
+use server::scopes::logs::logs_scope;
+
+use api::requests::logs::NewLog;
+use api::responses::load::LoadResponse;
+use api::responses::problem::ProblemResponse;
+use api::validation::FieldError;
+use api::validation::ValidationErrors;
+use api::validation::ValidationRules;
+
+mod mem_db;
+
+fn new_log(user_agent: &str, response_time: i32) -> NewLog {
+    NewLog {
+        user_agent: user_agent.into(),
+        response_time,
+        timestamp: None,
+        id: None,
+    }
+}
+
+fn field_error(field: &str, message: &str) -> FieldError {
+    FieldError {
+        field: field.into(),
+        message: message.into(),
+    }
+}
+
+#[actix_web::test]
+async fn validation_rules() {
+    let now = Utc.with_ymd_and_hms(2023, 1, 2, 3, 4, 5).unwrap();
+    let rules = ValidationRules::default();
+
+    let log = NewLog {
+        timestamp: Some(now + Duration::minutes(5)),
+        ..new_log(&"a".repeat(256), 0)
+    };
+    assert_eq!(rules.validate_at(log.clone(), now), Ok(log));
+
+    let cases = [
+        (
+            new_log("", 100),
+            vec![field_error("user_agent", "must not be empty")],
+        ),
+        (
+            new_log(&"a".repeat(257), -1),
+            vec![
+                field_error("user_agent", "must be at most 256 characters"),
+                field_error("response_time", "must not be negative"),
+            ],
+        ),
+        (
+            NewLog {
+                timestamp: Some(now + Duration::days(1)),
+                ..new_log("agent", 100)
+            },
+            vec![field_error(
+                "timestamp",
+                "must be at most 300 seconds in the future",
+            )],
+        ),
+    ];
+    for (log, errors) in cases {
+        assert_eq!(rules.validate_at(log, now), Err(ValidationErrors(errors)));
+    }
+
+    // the rules can be tightened, but user agents never get longer than the column
+    let rules = rules
+        .with_max_user_agent_len(1000)
+        .with_max_response_time(60_000)
+        .with_max_future(Duration::zero());
+    assert_eq!(rules.max_user_agent_len(), 256);
+    let log = NewLog {
+        timestamp: Some(now + Duration::seconds(1)),
+        ..new_log("agent", 60_001)
+    };
+    assert_eq!(
+        rules.validate_at(log, now).unwrap_err().to_string(),
+        "response_time: must be at most 60000; timestamp: must be at most 0 seconds in the future"
+    );
+
+    // near the end of time, any timestamp is within the limit
+    let rules = rules.with_max_future(Duration::seconds(ValidationRules::MAX_FUTURE_SECS));
+    let now = DateTime::<Utc>::MAX_UTC - Duration::days(1);
+    let log = NewLog {
+        timestamp: Some(DateTime::<Utc>::MAX_UTC),
+        ..new_log("agent", 100)
+    };
+    assert_eq!(rules.validate_at(log.clone(), now), Ok(log));
+}
+
+#[actix_web::test]
+async fn create_invalid_log() {
+    let mem_db = mem_db::MemDb::default();
+    let app_state = web::Data::new(mem_db);
+
+    let app = test::init_service(
+        App::new()
+            .app_data(app_state.clone())
+            .configure(logs_scope::<mem_db::MemDb>),
+    )
+    .await;
+    let req = test::TestRequest::post()
+        .uri("/logs")
+        .set_json(new_log(" ", -100))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+
+    assert_eq!(res.status(), http::StatusCode::UNPROCESSABLE_ENTITY);
+    let problem: ProblemResponse = test::read_body_json(res).await;
+    assert_eq!(
+        problem.errors,
+        vec![
+            field_error("user_agent", "must not be empty"),
+            field_error("response_time", "must not be negative"),
+        ]
+    );
+    assert!(app_state.logs.read().unwrap().is_empty());
+}
+
+#[actix_web::test]
+async fn create_invalid_logs_in_bulk() {
+    let ndjson = "{\"user_agent\":\"agent 1\",\"response_time\":100}\n\
+        {\"user_agent\":\"\",\"response_time\":200}\n";
+    let json = r#"[
+        {"user_agent": "agent 1", "response_time": 100},
+        {"user_agent": "", "response_time": 200}
+    ]"#;
+    let csv = "user_agent,response_time\nagent 1,100\n,200\n";
+
+    for (uri, content_type, body, line) in [
+        ("/logs/bulk", "application/x-ndjson", ndjson, 2),
+        ("/logs/bulk", "application/json", json, 2),
+        ("/logs", "application/x-ndjson", ndjson, 2),
+        ("/logs", "text/csv", csv, 3),
+    ] {
+        let mem_db = mem_db::MemDb::default();
+        let app_state = web::Data::new(mem_db);
+
+        let app = test::init_service(
+            App::new()
+                .app_data(app_state.clone())
+                .configure(logs_scope::<mem_db::MemDb>),
+        )
+        .await;
+        let req = test::TestRequest::post()
+            .uri(uri)
+            .append_header((http::header::CONTENT_TYPE, content_type))
+            .set_payload(body)
+            .to_request();
+        let res: LoadResponse = test::call_and_read_body_json(&app, req).await;
+
+        assert_eq!((res.accepted, res.rejected), (1, 1), "{uri} {content_type}");
+        assert_eq!(res.errors[0].line, line, "{uri} {content_type}");
+        assert_eq!(res.errors[0].error, "user_agent: must not be empty");
+
+        // the whole upload fails in strict mode
+        let req = test::TestRequest::post()
+            .uri(&format!("{uri}?mode=strict"))
+            .append_header((http::header::CONTENT_TYPE, content_type))
+            .set_payload(body)
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(
+            res.status(),
+            http::StatusCode::UNPROCESSABLE_ENTITY,
+            "{uri} {content_type}"
+        );
+    }
+}
+
+#[actix_web::test]
+async fn create_logs_with_configured_rules() {
+    let mem_db = mem_db::MemDb::default();
+    let app_state = web::Data::new(mem_db);
+    let rules = ValidationRules::default().with_max_response_time(1000);
+
+    let app = test::init_service(
+        App::new()
+            .app_data(app_state.clone())
+            .app_data(rules)
+            .configure(logs_scope::<mem_db::MemDb>),
+    )
+    .await;
+    for (response_time, status) in [
+        (1000, http::StatusCode::CREATED),
+        (1001, http::StatusCode::UNPROCESSABLE_ENTITY),
+    ] {
+        let req = test::TestRequest::post()
+            .uri("/logs")
+            .set_json(new_log("agent", response_time))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+
+        assert_eq!(res.status(), status, "{response_time}");
+    }
+}